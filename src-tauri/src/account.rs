@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Plan details shown on the user portal next to the data balance. Every
+/// field is optional since deployments differ in what they list.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub struct AccountDetails {
+    pub group: Option<String>,
+    pub policy: Option<String>,
+    pub expiry: Option<String>,
+    pub bandwidth: Option<String>,
+    pub upload: Option<String>,
+    pub download: Option<String>,
+}
+
+impl AccountDetails {
+    /// Fills in whatever is still missing from `other`, for details that are
+    /// spread over more than one portal page.
+    pub fn merge(&mut self, other: AccountDetails) {
+        self.group = self.group.take().or(other.group);
+        self.policy = self.policy.take().or(other.policy);
+        self.expiry = self.expiry.take().or(other.expiry);
+        self.bandwidth = self.bandwidth.take().or(other.bandwidth);
+        self.upload = self.upload.take().or(other.upload);
+        self.download = self.download.take().or(other.download);
+    }
+}
+
+fn cell_text(node: &tl::Node, parser: &tl::Parser) -> String {
+    node.inner_text(parser)
+        .replace("&nbsp;", " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Reads the label/value rows of a user portal page. The portal lays these
+/// out as table rows with the label in the first cell and the value in the
+/// next, so rows are matched on their label rather than their position.
+pub fn parse_account_details(page: &str) -> AccountDetails {
+    let mut details = AccountDetails::default();
+    let dom = tl::parse(page, tl::ParserOptions::default());
+    if dom.is_err() {
+        return details;
+    }
+    let dom = dom.unwrap();
+    let parser = dom.parser();
+    let rows = dom.query_selector("tr");
+    if rows.is_none() {
+        return details;
+    }
+    for row in rows.unwrap() {
+        let row = row.get(parser).and_then(|node| node.as_tag());
+        if row.is_none() {
+            continue;
+        }
+        let cells: Vec<String> = row
+            .unwrap()
+            .children()
+            .top()
+            .iter()
+            .filter_map(|child| child.get(parser))
+            .filter(|child| {
+                child
+                    .as_tag()
+                    .map(|tag| {
+                        tag.name().as_bytes().eq_ignore_ascii_case(b"td")
+                            || tag.name().as_bytes().eq_ignore_ascii_case(b"th")
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|cell| cell_text(cell, parser))
+            .collect();
+        if cells.len() < 2 || cells[1].is_empty() {
+            continue;
+        }
+        let label = cells[0].trim_end_matches(':').trim().to_lowercase();
+        let value = Some(cells[1].to_string());
+        let field = if label.contains("group") {
+            &mut details.group
+        } else if label.contains("policy") || label.contains("plan") {
+            &mut details.policy
+        } else if label.contains("expir") || label.contains("valid till") {
+            &mut details.expiry
+        } else if label.contains("upload") {
+            &mut details.upload
+        } else if label.contains("download") {
+            &mut details.download
+        } else if label.contains("bandwidth") {
+            &mut details.bandwidth
+        } else {
+            continue;
+        };
+        if field.is_none() {
+            *field = value;
+        }
+    }
+    details
+}
@@ -0,0 +1,203 @@
+use regex::Regex;
+use reqwest::blocking::Client;
+use std::sync::{Mutex, PoisonError};
+
+use super::{CaptiveError, CaptivePortal, LoginOutcome};
+
+/// FortiGate firewall authentication. Unauthenticated traffic is sent to
+/// `fgtauth?<magic>`, and the login form is posted back with that magic
+/// token. A successful login hands out keepalive and logout links.
+pub struct FortigatePortal {
+    endpoint: String,
+    keepalive_url: Mutex<Option<String>>,
+    logout_url: Mutex<Option<String>>,
+}
+
+impl FortigatePortal {
+    pub fn new(endpoint: &str) -> Self {
+        FortigatePortal {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            keepalive_url: Mutex::new(None),
+            logout_url: Mutex::new(None),
+        }
+    }
+
+    /// Fetches the login page to get a fresh magic token
+    fn magic(&self, client: &Client) -> Result<String, CaptiveError> {
+        let response = client
+            .get(self.endpoint.to_string() + "/fgtauth")
+            .send()
+            .map_err(|_| CaptiveError::Unreachable)?;
+        let from_url = response
+            .url()
+            .query()
+            .filter(|_| response.url().path().ends_with("fgtauth"))
+            .map(|query| query.to_string());
+        let body = response.text().map_err(|_| CaptiveError::Unreachable)?;
+        let from_form = Regex::new(r#"name="magic"\s+value="([^"]+)""#)
+            .unwrap()
+            .captures(&body)
+            .and_then(|captures| captures.get(1))
+            .map(|magic| magic.as_str().to_string());
+        from_form.or(from_url).ok_or(CaptiveError::Failed)
+    }
+}
+
+fn link(body: &str, kind: &str) -> Option<String> {
+    Regex::new(&format!(r#"(https?://[^"'\s]+/{}\?[0-9A-Za-z]+)"#, kind))
+        .unwrap()
+        .captures(body)
+        .and_then(|captures| captures.get(1))
+        .map(|url| url.as_str().to_string())
+}
+
+impl CaptivePortal for FortigatePortal {
+    fn detect(&self, client: &Client) -> bool {
+        client
+            .get(self.endpoint.to_string() + "/fgtauth")
+            .send()
+            .is_ok()
+    }
+
+    fn login(
+        &self,
+        client: &Client,
+        username: &str,
+        password: &str,
+    ) -> Result<LoginOutcome, CaptiveError> {
+        let magic = self.magic(client)?;
+        let body: String = format!(
+            "4Tredir=%2F&magic={}&username={}&password={}",
+            magic, username, password
+        );
+        let res_body = client
+            .post(self.endpoint.to_string() + "/")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|_| CaptiveError::Unreachable)?;
+        let keepalive_url = link(&res_body, "keepalive");
+        if keepalive_url.is_some() {
+            *self
+                .logout_url
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = link(&res_body, "logout");
+            *self
+                .keepalive_url
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = keepalive_url;
+            Ok(LoginOutcome::LoggedIn)
+        } else if res_body.to_lowercase().contains("authentication failed") {
+            Ok(LoginOutcome::InvalidCredentials)
+        } else if res_body.to_lowercase().contains("concurrent") {
+            Ok(LoginOutcome::LoginLimit)
+        } else {
+            Ok(LoginOutcome::Unknown)
+        }
+    }
+
+    fn logout(&self, client: &Client, _username: &str) -> Result<(), CaptiveError> {
+        let logout_url = self
+            .logout_url
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .unwrap_or(self.endpoint.to_string() + "/logout?");
+        client
+            .get(logout_url)
+            .send()
+            .map_err(|_| CaptiveError::Unreachable)?;
+        *self
+            .keepalive_url
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+        *self
+            .logout_url
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+        Ok(())
+    }
+
+    /// FortiGate drops logins whose keepalive page has not been loaded
+    /// within the authentication timeout
+    fn keepalive(&self, client: &Client, _username: &str) -> Result<(), CaptiveError> {
+        let keepalive_url = self
+            .keepalive_url
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if keepalive_url.is_none() {
+            return Err(CaptiveError::Failed);
+        }
+        let response = client
+            .get(keepalive_url.unwrap())
+            .send()
+            .map_err(|_| CaptiveError::Unreachable)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(CaptiveError::Failed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{Canned, FixtureServer};
+
+    fn serve(login_answer: &str) -> (FortigatePortal, FixtureServer) {
+        let server = FixtureServer::start(&[
+            (
+                "GET /fgtauth",
+                Canned::ok(include_str!("../../fixtures/fortigate_login.html")),
+            ),
+            ("POST /", Canned::ok(login_answer)),
+            ("GET /keepalive", Canned::ok("")),
+            ("GET /logout", Canned::ok("")),
+        ]);
+        (FortigatePortal::new(&server.endpoint), server)
+    }
+
+    #[test]
+    fn logs_in_with_the_magic_token() {
+        let (portal, server) = serve(include_str!("../../fixtures/fortigate_logged_in.html"));
+        let client = Client::new();
+        assert!(portal.login(&client, "f2026%40goa", "p%26ss") == Ok(LoginOutcome::LoggedIn));
+        let login = &server.received()[1];
+        assert_eq!(login.form("magic").as_deref(), Some("0a1b2c3d4e5f6789"));
+        assert_eq!(login.form("username").as_deref(), Some("f2026@goa"));
+        assert_eq!(login.form("password").as_deref(), Some("p&ss"));
+    }
+
+    #[test]
+    fn keeps_alive_and_logs_out_through_the_links_it_was_given() {
+        let (portal, server) = serve(include_str!("../../fixtures/fortigate_logged_in.html"));
+        let client = Client::new();
+        portal.login(&client, "user", "pass").ok();
+        assert!(portal.keepalive(&client, "user").is_ok());
+        assert!(portal.logout(&client, "user").is_ok());
+        let targets: Vec<String> = server
+            .received()
+            .iter()
+            .map(|request| request.target.to_string())
+            .collect();
+        assert_eq!(
+            targets[2..],
+            ["/keepalive?020b070e0d0a0f01", "/logout?020b070e0d0a0f01"]
+        );
+        // The links are gone with the login
+        assert!(portal.keepalive(&client, "user") == Err(CaptiveError::Failed));
+    }
+
+    #[test]
+    fn reads_refusals() {
+        let (portal, _server) = serve(include_str!("../../fixtures/fortigate_failed.html"));
+        let outcome = portal.login(&Client::new(), "user", "pass");
+        assert!(outcome == Ok(LoginOutcome::InvalidCredentials));
+        let (portal, _server) = serve("<p>Concurrent login limit reached</p>");
+        let outcome = portal.login(&Client::new(), "user", "pass");
+        assert!(outcome == Ok(LoginOutcome::LoginLimit));
+    }
+}
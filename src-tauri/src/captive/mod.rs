@@ -0,0 +1,167 @@
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use ts_rs::TS;
+
+use crate::quota::Quota;
+
+mod fortigate;
+mod pfsense;
+pub mod recipe;
+mod sophos;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Vendor {
+    Sophos,
+    Fortigate,
+    Pfsense,
+    /// Any other portal, described by a recipe file
+    Recipe,
+}
+
+/// The network the app logs in to and which captive portal guards it
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(default)]
+pub struct CampusProfile {
+    pub name: String,
+    pub vendor: Vendor,
+    pub login_endpoint: String,
+    /// Sophos user portal, where usage and account details are read from
+    pub portal_endpoint: String,
+    /// Captive portal zone, only used by pfSense
+    pub zone: Option<String>,
+    /// Recipe file, relative to the config directory unless absolute. Only
+    /// used by the recipe vendor.
+    pub recipe: Option<String>,
+}
+
+impl Default for CampusProfile {
+    fn default() -> Self {
+        CampusProfile {
+            name: "BITS Goa".to_string(),
+            vendor: Vendor::Sophos,
+            login_endpoint: "https://campnet.bits-goa.ac.in:8090".to_string(),
+            portal_endpoint: "https://campnet.bits-goa.ac.in:4443".to_string(),
+            zone: None,
+            recipe: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoginOutcome {
+    LoggedIn,
+    InvalidCredentials,
+    /// Too many devices are logged in with the same account
+    LoginLimit,
+    DataExceeded,
+    /// The portal answered with something we do not understand
+    Unknown,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CaptiveError {
+    /// The request did not go through
+    Unreachable,
+    /// The portal answered but did not do what was asked
+    Failed,
+    /// The portal has no way of doing this
+    Unsupported,
+}
+
+/// A captive portal vendor's login protocol. Credentials are passed URL
+/// encoded, the same way they are saved.
+pub trait CaptivePortal: Send + Sync {
+    /// Whether the portal answers, which means we are behind it
+    fn detect(&self, client: &Client) -> bool;
+
+    fn login(
+        &self,
+        client: &Client,
+        username: &str,
+        password: &str,
+    ) -> Result<LoginOutcome, CaptiveError>;
+
+    fn logout(&self, client: &Client, username: &str) -> Result<(), CaptiveError>;
+
+    /// Keeps the current login from timing out, for portals that expire
+    /// idle logins
+    fn keepalive(&self, _client: &Client, _username: &str) -> Result<(), CaptiveError> {
+        Ok(())
+    }
+
+    /// Quotas the captive portal itself reports. Sophos reports usage on its
+    /// separate user portal instead, see `portal`.
+    fn usage(
+        &self,
+        _client: &Client,
+        _username: &str,
+        _password: &str,
+    ) -> Result<Vec<Quota>, CaptiveError> {
+        Err(CaptiveError::Unsupported)
+    }
+}
+
+/// Portal of the built in campus, which needs no files to load
+pub fn built_in() -> Arc<dyn CaptivePortal> {
+    Arc::new(sophos::SophosPortal::new(
+        &CampusProfile::default().login_endpoint,
+    ))
+}
+
+pub fn for_profile(
+    profile: &CampusProfile,
+    config_dir: &Path,
+) -> Result<Arc<dyn CaptivePortal>, String> {
+    Ok(match profile.vendor {
+        Vendor::Sophos => Arc::new(sophos::SophosPortal::new(&profile.login_endpoint)),
+        Vendor::Fortigate => Arc::new(fortigate::FortigatePortal::new(&profile.login_endpoint)),
+        Vendor::Pfsense => Arc::new(pfsense::PfsensePortal::new(
+            &profile.login_endpoint,
+            profile.zone.as_deref().unwrap_or_default(),
+        )),
+        Vendor::Recipe => {
+            let path = profile
+                .recipe
+                .as_ref()
+                .ok_or(format!("{} has no recipe file set", profile.name))?;
+            let recipe = recipe::load_recipe(&config_dir.join(path))?;
+            Arc::new(recipe::RecipePortal::new(recipe, entered_endpoint(profile)))
+        }
+    })
+}
+
+/// The login endpoint, unless it is still the Sophos one profiles start out
+/// with, which only stands in for one the user has not entered
+fn entered_endpoint(profile: &CampusProfile) -> Option<&str> {
+    let default_endpoint = CampusProfile::default().login_endpoint;
+    Some(profile.login_endpoint.as_str())
+        .filter(|endpoint| !endpoint.is_empty() && *endpoint != default_endpoint)
+}
+
+fn timestamp() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_an_entered_endpoint_overrides_the_recipe() {
+        let mut profile = CampusProfile {
+            vendor: Vendor::Recipe,
+            ..CampusProfile::default()
+        };
+        assert_eq!(entered_endpoint(&profile), None);
+        profile.login_endpoint = "".to_string();
+        assert_eq!(entered_endpoint(&profile), None);
+        profile.login_endpoint = "http://127.0.0.1:8000".to_string();
+        assert_eq!(entered_endpoint(&profile), Some("http://127.0.0.1:8000"));
+    }
+}
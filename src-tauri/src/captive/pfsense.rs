@@ -0,0 +1,138 @@
+use regex::Regex;
+use reqwest::blocking::Client;
+use std::sync::{Mutex, PoisonError};
+
+use super::{CaptiveError, CaptivePortal, LoginOutcome};
+
+/// pfSense captive portal. The login form is posted to `index.php` with the
+/// portal zone, and the page shown after logging in carries the id needed
+/// to log out again.
+pub struct PfsensePortal {
+    endpoint: String,
+    zone: String,
+    logout_id: Mutex<Option<String>>,
+}
+
+impl PfsensePortal {
+    pub fn new(endpoint: &str, zone: &str) -> Self {
+        PfsensePortal {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            zone: zone.to_string(),
+            logout_id: Mutex::new(None),
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("{}/index.php?zone={}", self.endpoint, self.zone)
+    }
+
+    fn post(&self, client: &Client, body: String) -> Result<String, CaptiveError> {
+        client
+            .post(self.url())
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|_| CaptiveError::Unreachable)
+    }
+}
+
+impl CaptivePortal for PfsensePortal {
+    fn detect(&self, client: &Client) -> bool {
+        client.get(self.url()).send().is_ok()
+    }
+
+    fn login(
+        &self,
+        client: &Client,
+        username: &str,
+        password: &str,
+    ) -> Result<LoginOutcome, CaptiveError> {
+        let body: String = format!(
+            "auth_user={}&auth_pass={}&zone={}&redirurl=%2F&accept=Login",
+            username, password, self.zone
+        );
+        let res_body = self.post(client, body)?;
+        let logout_id = Regex::new(r#"name="logout_id"\s+(?:type="hidden"\s+)?value="([^"]+)""#)
+            .unwrap()
+            .captures(&res_body)
+            .and_then(|captures| captures.get(1))
+            .map(|id| id.as_str().to_string());
+        if logout_id.is_some() {
+            *self
+                .logout_id
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = logout_id;
+            Ok(LoginOutcome::LoggedIn)
+        } else if res_body.to_lowercase().contains("invalid credentials") {
+            Ok(LoginOutcome::InvalidCredentials)
+        } else if res_body.to_lowercase().contains("concurrent") {
+            Ok(LoginOutcome::LoginLimit)
+        } else if res_body.to_lowercase().contains("quota") {
+            Ok(LoginOutcome::DataExceeded)
+        } else {
+            Ok(LoginOutcome::Unknown)
+        }
+    }
+
+    fn logout(&self, client: &Client, _username: &str) -> Result<(), CaptiveError> {
+        let logout_id = self
+            .logout_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if logout_id.is_none() {
+            return Err(CaptiveError::Failed);
+        }
+        let body: String = format!(
+            "logout_id={}&zone={}&logout=Logout",
+            logout_id.unwrap(),
+            self.zone
+        );
+        self.post(client, body)?;
+        *self
+            .logout_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{Canned, FixtureServer};
+
+    fn serve(login_answer: &str) -> (PfsensePortal, FixtureServer) {
+        let server = FixtureServer::start(&[("POST /index.php", Canned::ok(login_answer))]);
+        (PfsensePortal::new(&server.endpoint, "campus"), server)
+    }
+
+    #[test]
+    fn logs_out_with_the_id_from_the_login_page() {
+        let (portal, server) = serve(include_str!("../../fixtures/pfsense_logged_in.html"));
+        let client = Client::new();
+        assert!(portal.login(&client, "user", "pass") == Ok(LoginOutcome::LoggedIn));
+        assert!(portal.logout(&client, "user").is_ok());
+        let received = server.received();
+        assert_eq!(received[0].target, "/index.php?zone=campus");
+        assert_eq!(received[0].form("auth_user").as_deref(), Some("user"));
+        assert_eq!(received[0].form("zone").as_deref(), Some("campus"));
+        assert_eq!(
+            received[1].form("logout_id").as_deref(),
+            Some("5f2c8e41d07a")
+        );
+        // Logging out again has nothing to go on
+        assert!(portal.logout(&client, "user") == Err(CaptiveError::Failed));
+    }
+
+    #[test]
+    fn reads_refusals() {
+        let (portal, _server) = serve(include_str!("../../fixtures/pfsense_invalid.html"));
+        let outcome = portal.login(&Client::new(), "user", "pass");
+        assert!(outcome == Ok(LoginOutcome::InvalidCredentials));
+        let (portal, _server) = serve("<p>Quota exceeded for this user</p>");
+        let outcome = portal.login(&Client::new(), "user", "pass");
+        assert!(outcome == Ok(LoginOutcome::DataExceeded));
+    }
+}
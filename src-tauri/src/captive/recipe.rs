@@ -0,0 +1,424 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use regex::Regex;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::{timestamp, CaptiveError, CaptivePortal, LoginOutcome};
+
+/// A captive portal described as a list of HTTP steps, for the small form
+/// based portals that do not deserve code of their own. Recipes are written
+/// in TOML or JSON, see `resources/recipes/example.toml`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Recipe {
+    pub name: String,
+    /// Prefix for step URLs that start with `/`. A login endpoint entered
+    /// in the campus profile takes its place, which is how a recipe is
+    /// pointed at a local mock portal.
+    pub base_url: String,
+    /// Page that only loads from behind the portal. The first step's URL is
+    /// used when left out.
+    pub detect: Option<String>,
+    pub login: Vec<Step>,
+    pub logout: Vec<Step>,
+    pub keepalive: Vec<Step>,
+    pub matchers: Matchers,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    /// Loads a page, which later steps extract from and match against
+    Fetch { url: String },
+    /// Saves part of the last page as `{name}` for later steps, either the
+    /// first capture group of `regex` or the node found by `selector`. The
+    /// node's text is taken unless `attribute` names one to read instead.
+    Extract {
+        name: String,
+        regex: Option<String>,
+        selector: Option<String>,
+        attribute: Option<String>,
+    },
+    /// Sends a form. Field values are templates, like URLs.
+    Submit {
+        url: String,
+        #[serde(default = "default_method")]
+        method: String,
+        #[serde(default)]
+        fields: BTreeMap<String, String>,
+    },
+}
+
+fn default_method() -> String {
+    "post".to_string()
+}
+
+/// Regular expressions matched against the page the login steps end on.
+/// Failures are checked first, so a page reading "login failed, try again
+/// to log in" is not mistaken for a successful one.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Matchers {
+    pub success: Vec<String>,
+    pub invalid_credentials: Vec<String>,
+    pub login_limit: Vec<String>,
+    pub data_exceeded: Vec<String>,
+}
+
+/// Reads a recipe, as TOML when the file ends in `.toml` and JSON otherwise
+pub fn load_recipe(path: &std::path::Path) -> Result<Recipe, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+    let recipe: Result<Recipe, String> =
+        if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+            toml::from_str(&contents).map_err(|err| err.to_string())
+        } else {
+            serde_json::from_str(&contents).map_err(|err| err.to_string())
+        };
+    let recipe = recipe.map_err(|err| format!("Invalid recipe {}: {}", path.display(), err))?;
+    recipe.validate()?;
+    Ok(recipe)
+}
+
+impl Recipe {
+    /// Catches mistakes when loading rather than halfway through a login
+    fn validate(&self) -> Result<(), String> {
+        if self.login.is_empty() {
+            return Err(format!("Recipe {} has no login steps", self.name));
+        }
+        for step in self.login.iter().chain(&self.logout).chain(&self.keepalive) {
+            if let Step::Extract {
+                name,
+                regex,
+                selector,
+                ..
+            } = step
+            {
+                if regex.is_some() == selector.is_some() {
+                    return Err(format!(
+                        "Extracting {} needs exactly one of regex or selector",
+                        name
+                    ));
+                }
+                if let Some(regex) = regex {
+                    Regex::new(regex).map_err(|err| err.to_string())?;
+                }
+            }
+        }
+        let matchers = &self.matchers;
+        for pattern in matchers
+            .success
+            .iter()
+            .chain(&matchers.invalid_credentials)
+            .chain(&matchers.login_limit)
+            .chain(&matchers.data_exceeded)
+        {
+            Regex::new(pattern).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// The last page a run of steps loaded
+#[derive(Default)]
+struct Page {
+    url: String,
+    body: String,
+}
+
+pub struct RecipePortal {
+    recipe: Recipe,
+}
+
+impl RecipePortal {
+    pub fn new(recipe: Recipe, endpoint: Option<&str>) -> Self {
+        let mut recipe = recipe;
+        if let Some(endpoint) = endpoint {
+            recipe.base_url = endpoint.to_string();
+        }
+        RecipePortal { recipe }
+    }
+
+    /// Renders a step URL. A template that is nothing but one placeholder
+    /// takes the value as is, for links extracted from an earlier page.
+    fn url(&self, template: &str, vars: &HashMap<String, String>) -> String {
+        let whole = template
+            .strip_prefix('{')
+            .and_then(|name| name.strip_suffix('}'))
+            .and_then(|name| vars.get(name));
+        let url = match whole {
+            Some(value) => value.to_string(),
+            None => render(template, vars),
+        };
+        if url.starts_with('/') {
+            self.recipe.base_url.trim_end_matches('/').to_string() + &url
+        } else {
+            url
+        }
+    }
+
+    /// Runs `steps` in order, stopping at the first one that fails
+    fn run(
+        &self,
+        client: &Client,
+        steps: &[Step],
+        vars: &mut HashMap<String, String>,
+    ) -> Result<Page, CaptiveError> {
+        let mut page = Page::default();
+        for step in steps {
+            vars.insert("timestamp".to_string(), timestamp().to_string());
+            match step {
+                Step::Fetch { url } => {
+                    page = fetch(client.get(self.url(url, vars)))?;
+                }
+                Step::Extract {
+                    name,
+                    regex,
+                    selector,
+                    attribute,
+                } => {
+                    let value = match (regex, selector) {
+                        (Some(regex), _) => extract_regex(&page.body, regex),
+                        (None, Some(selector)) => {
+                            extract_selector(&page.body, selector, attribute.as_deref())
+                        }
+                        (None, None) => None,
+                    };
+                    vars.insert(name.to_string(), value.ok_or(CaptiveError::Failed)?);
+                }
+                Step::Submit {
+                    url,
+                    method,
+                    fields,
+                } => {
+                    let body = fields
+                        .iter()
+                        .map(|(name, value)| {
+                            format!(
+                                "{}={}",
+                                utf8_percent_encode(name, NON_ALPHANUMERIC),
+                                render(value, vars)
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join("&");
+                    let url = self.url(url, vars);
+                    page = if method.eq_ignore_ascii_case("get") {
+                        let separator = if url.contains('?') { '&' } else { '?' };
+                        fetch(client.get(format!("{}{}{}", url, separator, body)))?
+                    } else {
+                        fetch(
+                            client
+                                .post(url)
+                                .header("Content-Type", "application/x-www-form-urlencoded")
+                                .body(body),
+                        )?
+                    };
+                }
+            }
+            vars.insert("page_url".to_string(), page.url.to_string());
+        }
+        Ok(page)
+    }
+}
+
+fn fetch(request: reqwest::blocking::RequestBuilder) -> Result<Page, CaptiveError> {
+    let response = request.send().map_err(|_| CaptiveError::Unreachable)?;
+    let url = response.url().to_string();
+    let body = response.text().map_err(|_| CaptiveError::Unreachable)?;
+    Ok(Page { url, body })
+}
+
+/// Fills in `{name}` placeholders with URL encoded values, since templates
+/// end up in URLs and form bodies. Unknown placeholders are left alone.
+fn render(template: &str, vars: &HashMap<String, String>) -> String {
+    let mut rendered = template.to_string();
+    for (name, value) in vars {
+        rendered = rendered.replace(
+            &format!("{{{}}}", name),
+            &utf8_percent_encode(value, NON_ALPHANUMERIC).to_string(),
+        );
+    }
+    rendered
+}
+
+fn extract_regex(body: &str, pattern: &str) -> Option<String> {
+    let captures = Regex::new(pattern).ok()?.captures(body)?;
+    captures
+        .get(1)
+        .or(captures.get(0))
+        .map(|value| value.as_str().to_string())
+}
+
+fn extract_selector(body: &str, selector: &str, attribute: Option<&str>) -> Option<String> {
+    let dom = tl::parse(body, tl::ParserOptions::default()).ok()?;
+    let parser = dom.parser();
+    let node = dom.query_selector(selector)?.next()?.get(parser)?;
+    match attribute {
+        Some(attribute) => node
+            .as_tag()?
+            .attributes()
+            .get(attribute)??
+            .try_as_utf8_str()
+            .map(|value| value.to_string()),
+        None => Some(node.inner_text(parser).trim().to_string()),
+    }
+}
+
+fn matches(patterns: &[String], body: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        Regex::new(pattern)
+            .map(|regex| regex.is_match(body))
+            .unwrap_or(false)
+    })
+}
+
+/// Saved credentials are URL encoded, recipes template the plain values
+fn credentials(username: &str, password: Option<&str>) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert(
+        "username".to_string(),
+        percent_decode_str(username).decode_utf8_lossy().to_string(),
+    );
+    if let Some(password) = password {
+        vars.insert(
+            "password".to_string(),
+            percent_decode_str(password).decode_utf8_lossy().to_string(),
+        );
+    }
+    vars
+}
+
+impl CaptivePortal for RecipePortal {
+    fn detect(&self, client: &Client) -> bool {
+        let url = match (&self.recipe.detect, self.recipe.login.first()) {
+            (Some(url), _) => url.to_string(),
+            (None, Some(Step::Fetch { url })) | (None, Some(Step::Submit { url, .. })) => {
+                url.to_string()
+            }
+            _ => return false,
+        };
+        client.get(self.url(&url, &HashMap::new())).send().is_ok()
+    }
+
+    fn login(
+        &self,
+        client: &Client,
+        username: &str,
+        password: &str,
+    ) -> Result<LoginOutcome, CaptiveError> {
+        let mut vars = credentials(username, Some(password));
+        let page = self.run(client, &self.recipe.login, &mut vars)?;
+        let matchers = &self.recipe.matchers;
+        Ok(if matches(&matchers.invalid_credentials, &page.body) {
+            LoginOutcome::InvalidCredentials
+        } else if matches(&matchers.login_limit, &page.body) {
+            LoginOutcome::LoginLimit
+        } else if matches(&matchers.data_exceeded, &page.body) {
+            LoginOutcome::DataExceeded
+        } else if matches(&matchers.success, &page.body) {
+            LoginOutcome::LoggedIn
+        } else {
+            LoginOutcome::Unknown
+        })
+    }
+
+    fn logout(&self, client: &Client, username: &str) -> Result<(), CaptiveError> {
+        if self.recipe.logout.is_empty() {
+            return Err(CaptiveError::Unsupported);
+        }
+        self.run(
+            client,
+            &self.recipe.logout,
+            &mut credentials(username, None),
+        )
+        .map(|_| ())
+    }
+
+    fn keepalive(&self, client: &Client, username: &str) -> Result<(), CaptiveError> {
+        self.run(
+            client,
+            &self.recipe.keepalive,
+            &mut credentials(username, None),
+        )
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{Canned, FixtureServer};
+
+    const LOGIN_PAGE: &str = r#"<form method="post" action="/login">
+        <input name="user"><input name="pass" type="password">
+        <input name="token" type="hidden" value="c0ffee">
+    </form>"#;
+
+    fn example() -> Recipe {
+        let recipe: Recipe =
+            toml::from_str(include_str!("../../resources/recipes/example.toml")).unwrap();
+        recipe.validate().unwrap();
+        recipe
+    }
+
+    fn serve(login_answer: &str) -> (RecipePortal, FixtureServer) {
+        let server = FixtureServer::start(&[
+            ("GET /login", Canned::ok(LOGIN_PAGE)),
+            ("POST /login", Canned::ok(login_answer)),
+            ("GET /logout", Canned::ok("Logged out")),
+        ]);
+        (RecipePortal::new(example(), Some(&server.endpoint)), server)
+    }
+
+    #[test]
+    fn keeps_its_base_url_without_an_endpoint() {
+        let portal = RecipePortal::new(example(), None);
+        assert_eq!(
+            portal.url("/login", &HashMap::new()),
+            "http://portal.example.net/login"
+        );
+    }
+
+    #[test]
+    fn logs_in_with_the_extracted_token() {
+        let (portal, server) = serve("<h1>You are now connected</h1>");
+        let outcome = portal.login(&Client::new(), "guest%40example", "p%26ss");
+        assert!(outcome == Ok(LoginOutcome::LoggedIn));
+        let submitted = &server.received()[1];
+        assert_eq!(submitted.form("token").as_deref(), Some("c0ffee"));
+        assert_eq!(submitted.form("user").as_deref(), Some("guest@example"));
+        assert_eq!(submitted.form("pass").as_deref(), Some("p&ss"));
+        assert_eq!(submitted.form("accept").as_deref(), Some("yes"));
+    }
+
+    #[test]
+    fn failures_win_over_success() {
+        let (portal, _server) = serve("Invalid password. Welcome back when you have it");
+        let outcome = portal.login(&Client::new(), "guest", "pass");
+        assert!(outcome == Ok(LoginOutcome::InvalidCredentials));
+        let (portal, _server) = serve("Your quota exceeded its limit");
+        let outcome = portal.login(&Client::new(), "guest", "pass");
+        assert!(outcome == Ok(LoginOutcome::DataExceeded));
+        let (portal, _server) = serve("Something else entirely");
+        let outcome = portal.login(&Client::new(), "guest", "pass");
+        assert!(outcome == Ok(LoginOutcome::Unknown));
+    }
+
+    #[test]
+    fn missing_token_fails_the_login() {
+        let server =
+            FixtureServer::start(&[("GET /login", Canned::ok("<p>Down for maintenance</p>"))]);
+        let portal = RecipePortal::new(example(), Some(&server.endpoint));
+        let outcome = portal.login(&Client::new(), "guest", "pass");
+        assert!(outcome == Err(CaptiveError::Failed));
+    }
+
+    #[test]
+    fn logs_out_with_a_query() {
+        let (portal, server) = serve("");
+        assert!(portal.logout(&Client::new(), "guest%40example").is_ok());
+        assert_eq!(server.received()[0].target, "/logout?user=guest%40example");
+    }
+}
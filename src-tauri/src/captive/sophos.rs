@@ -0,0 +1,87 @@
+use reqwest::blocking::Client;
+
+use super::{timestamp, CaptiveError, CaptivePortal, LoginOutcome};
+
+/// Sophos captive portal, spoken to the way the Sophos client does through
+/// `login.xml`, `logout.xml` and `live`.
+pub struct SophosPortal {
+    endpoint: String,
+}
+
+impl SophosPortal {
+    pub fn new(endpoint: &str) -> Self {
+        SophosPortal {
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    fn post(&self, client: &Client, path: &str, body: String) -> Result<String, CaptiveError> {
+        client
+            .post(self.endpoint.to_string() + path)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Content-Length", body.chars().count())
+            .body(body)
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|_| CaptiveError::Unreachable)
+    }
+}
+
+impl CaptivePortal for SophosPortal {
+    fn detect(&self, client: &Client) -> bool {
+        client.head(self.endpoint.to_string()).send().is_ok()
+    }
+
+    fn login(
+        &self,
+        client: &Client,
+        username: &str,
+        password: &str,
+    ) -> Result<LoginOutcome, CaptiveError> {
+        let body: String = format!(
+            "mode=191&username={}&password={}&a={}&producttype=1",
+            username,
+            password,
+            timestamp()
+        );
+        let res_body = self.post(client, "/login.xml", body)?;
+        Ok(if res_body.contains("LIVE") {
+            LoginOutcome::LoggedIn
+        } else if res_body.to_lowercase().contains("login limit") {
+            LoginOutcome::LoginLimit
+        } else if res_body.contains("failed") {
+            LoginOutcome::InvalidCredentials
+        } else if res_body.contains("exceeded") {
+            LoginOutcome::DataExceeded
+        } else {
+            LoginOutcome::Unknown
+        })
+    }
+
+    fn logout(&self, client: &Client, username: &str) -> Result<(), CaptiveError> {
+        let body: String = format!(
+            "mode=193&username={}&a={}&producttype=1",
+            username,
+            timestamp()
+        );
+        self.post(client, "/logout.xml", body).map(|_| ())
+    }
+
+    fn keepalive(&self, client: &Client, username: &str) -> Result<(), CaptiveError> {
+        let response = client
+            .get(format!(
+                "{}/live?mode=192&username={}&a={}&producttype=1",
+                self.endpoint,
+                username,
+                timestamp()
+            ))
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|_| CaptiveError::Unreachable)?;
+        if response.contains("LIVE") || response.contains("<ack>") {
+            Ok(())
+        } else {
+            Err(CaptiveError::Failed)
+        }
+    }
+}
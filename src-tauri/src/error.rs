@@ -0,0 +1,210 @@
+use serde::{Serialize, Serializer};
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Error logs are moved aside once they grow past this many bytes
+const LOG_LIMIT: u64 = 1024 * 1024;
+
+/// Longest wait before a failed background loop is started again
+const MAX_RESTART_SECS: u64 = 300;
+
+/// Directory `report` appends `errors.log` to, set once the app knows it
+static LOG_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Anything that goes wrong outside of talking to the portals, which have
+/// errors of their own. None of these are worth taking the app down for.
+#[derive(Debug)]
+pub enum AppError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Tauri(tauri::Error),
+    /// Notifications, the clipboard and other OS integration
+    Api(tauri::api::Error),
+    AutoLaunch(auto_launch::Error),
+    /// The main window is gone, which happens while the app shuts down
+    NoWindow,
+    /// Tauri could not work out a directory, usually for lack of a home
+    /// directory
+    NoPath(&'static str),
+    /// A background loop panicked, with how many times so far
+    Panicked(u32),
+    Other(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Io(err) => write!(f, "{}", err),
+            AppError::Json(err) => write!(f, "Malformed JSON: {}", err),
+            AppError::Tauri(err) => write!(f, "{}", err),
+            AppError::Api(err) => write!(f, "{}", err),
+            AppError::AutoLaunch(err) => write!(f, "Could not change start at boot: {}", err),
+            AppError::NoWindow => write!(f, "The main window is not open"),
+            AppError::NoPath(dir) => write!(f, "Could not find the {} directory", dir),
+            AppError::Panicked(times) => write!(f, "Stopped unexpectedly {} time(s)", times),
+            AppError::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        AppError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Json(err)
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(err: tauri::Error) -> Self {
+        AppError::Tauri(err)
+    }
+}
+
+impl From<tauri::api::Error> for AppError {
+    fn from(err: tauri::api::Error) -> Self {
+        AppError::Api(err)
+    }
+}
+
+impl From<auto_launch::Error> for AppError {
+    fn from(err: auto_launch::Error) -> Self {
+        AppError::AutoLaunch(err)
+    }
+}
+
+impl From<String> for AppError {
+    fn from(err: String) -> Self {
+        AppError::Other(err)
+    }
+}
+
+/// Commands hand the message to the window
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// For results nothing else depends on, like updating the tray or showing a
+/// notification: the error is logged and the app carries on.
+pub trait OrReport<T> {
+    fn or_report(self, context: &str) -> Option<T>;
+}
+
+impl<T, E: Into<AppError>> OrReport<T> for Result<T, E> {
+    fn or_report(self, context: &str) -> Option<T> {
+        match self {
+            Ok(value) => Some(value),
+            Err(err) => {
+                report(context, &err.into());
+                None
+            }
+        }
+    }
+}
+
+/// Sets where errors and crash reports are written
+pub fn init(log_dir: PathBuf, crash_dir: PathBuf, version: String) {
+    *LOG_DIR
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(log_dir);
+    install_panic_hook(crash_dir, version);
+}
+
+/// Writes `err` to stderr and to `errors.log` in the log directory
+pub fn report(context: &str, err: &AppError) {
+    let line = format!(
+        "[{}] {}: {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        context,
+        err
+    );
+    eprintln!("{}", line);
+    let log_dir = LOG_DIR
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    if let Some(log_dir) = log_dir {
+        append(&log_dir.join("errors.log"), &line).ok();
+    }
+}
+
+fn append(log_file: &std::path::Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = log_file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::metadata(log_file)
+        .map(|meta| meta.len())
+        .unwrap_or(0)
+        > LOG_LIMIT
+    {
+        std::fs::rename(log_file, log_file.with_extension("log.old"))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)?;
+    writeln!(file, "{}", line)
+}
+
+/// Writes `crash-<time>.txt` to `dir` for every panic, then lets the default
+/// hook print the message as before
+fn install_panic_hook(dir: PathBuf, version: String) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let now = chrono::Local::now();
+        let thread = std::thread::current();
+        let crash_report = format!(
+            "Auto Campnet {} crashed at {}\nOS: {} {}\nThread: {}\n{}\n\n{}\n",
+            version,
+            now.to_rfc3339(),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            thread.name().unwrap_or("unnamed"),
+            info,
+            std::backtrace::Backtrace::force_capture()
+        );
+        let written = std::fs::create_dir_all(&dir).and_then(|_| {
+            std::fs::write(
+                dir.join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S"))),
+                crash_report,
+            )
+        });
+        if written.is_err() {
+            eprintln!("Could not write a crash report to {}", dir.display());
+        }
+        default_hook(info);
+    }));
+}
+
+/// How long to wait before running a loop again after it failed `failures`
+/// times
+fn restart_delay(failures: u32) -> Duration {
+    Duration::from_secs((5u64 << failures.min(6)).min(MAX_RESTART_SECS))
+}
+
+/// Runs `body` until it returns, running it again after a panic instead of
+/// losing the loop for the rest of the session. `recover` runs right after
+/// the panic to put shared state back in order, so that nothing else trips
+/// over it while waiting to restart.
+pub fn supervise(name: &str, body: impl Fn(), recover: impl Fn()) {
+    let mut failures = 0;
+    while catch_unwind(AssertUnwindSafe(&body)).is_err() {
+        failures += 1;
+        recover();
+        report(name, &AppError::Panicked(failures));
+        std::thread::sleep(restart_delay(failures));
+    }
+}
@@ -0,0 +1,153 @@
+//! A local HTTP server answering with canned responses, for testing the
+//! portal clients against recorded pages
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// Canned answer to one `METHOD /path` route
+#[derive(Clone)]
+pub struct Canned {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl Canned {
+    pub fn ok(body: &str) -> Self {
+        Canned::status(200, body)
+    }
+
+    pub fn status(status: u16, body: &str) -> Self {
+        Canned {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+}
+
+/// A request the server received
+#[derive(Clone, Debug)]
+pub struct Received {
+    pub method: String,
+    /// Path with the query string
+    pub target: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Received {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    /// Value of a field of a form or query string body
+    pub fn form(&self, name: &str) -> Option<String> {
+        self.body
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| {
+                percent_encoding::percent_decode_str(&value.replace('+', " "))
+                    .decode_utf8_lossy()
+                    .to_string()
+            })
+    }
+}
+
+pub struct FixtureServer {
+    pub endpoint: String,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl FixtureServer {
+    /// Serves `routes`, keyed as `GET /path`. Anything else gets a 404.
+    /// `ENDPOINT` in a body is replaced with the server's own address, for
+    /// pages that link back to it.
+    pub fn start(routes: &[(&str, Canned)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let routes: HashMap<String, Canned> = routes
+            .iter()
+            .map(|(route, canned)| {
+                let mut canned = canned.clone();
+                canned.body = canned.body.replace("ENDPOINT", &endpoint);
+                (route.to_string(), canned)
+            })
+            .collect();
+        let received: Arc<Mutex<Vec<Received>>> = Arc::default();
+        let log = received.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let request = match read_request(&mut stream) {
+                    Some(request) => request,
+                    None => continue,
+                };
+                let canned = routes
+                    .get(&format!("{} {}", request.method, request.path()))
+                    .cloned()
+                    .unwrap_or_else(|| Canned::status(404, ""));
+                log.lock().unwrap().push(request);
+                let mut response = format!(
+                    "HTTP/1.1 {} Canned\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    canned.status,
+                    canned.body.len()
+                );
+                for (name, value) in canned.headers.iter() {
+                    response += &format!("{}: {}\r\n", name, value);
+                }
+                response += "\r\n";
+                response += &canned.body;
+                stream.write_all(response.as_bytes()).ok();
+            }
+        });
+        FixtureServer { endpoint, received }
+    }
+
+    pub fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &mut std::net::TcpStream) -> Option<Received> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut headers: HashMap<String, String> = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(Received {
+        method,
+        target,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
@@ -0,0 +1,300 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use ts_rs::TS;
+
+/// Hook logs are moved aside once they grow past this many bytes
+const LOG_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    Login,
+    Logout,
+    LoginFailed,
+    QuotaThreshold,
+    QuotaReset,
+    NetworkLost,
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::Login => "login",
+            HookEvent::Logout => "logout",
+            HookEvent::LoginFailed => "login_failed",
+            HookEvent::QuotaThreshold => "quota_threshold",
+            HookEvent::QuotaReset => "quota_reset",
+            HookEvent::NetworkLost => "network_lost",
+        }
+    }
+}
+
+/// Commands to run when things happen, passed to `sh -c` (`cmd /C` on
+/// Windows). Events without a command are skipped.
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(default)]
+pub struct HookSettings {
+    pub on_login: Option<String>,
+    pub on_logout: Option<String>,
+    pub on_login_failed: Option<String>,
+    pub on_quota_threshold: Option<String>,
+    pub on_quota_reset: Option<String>,
+    pub on_network_lost: Option<String>,
+    /// Seconds a command may run before it is killed
+    #[ts(type = "number")]
+    pub timeout_secs: u64,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings {
+            on_login: None,
+            on_logout: None,
+            on_login_failed: None,
+            on_quota_threshold: None,
+            on_quota_reset: None,
+            on_network_lost: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl HookSettings {
+    fn command(&self, event: HookEvent) -> Option<&String> {
+        match event {
+            HookEvent::Login => self.on_login.as_ref(),
+            HookEvent::Logout => self.on_logout.as_ref(),
+            HookEvent::LoginFailed => self.on_login_failed.as_ref(),
+            HookEvent::QuotaThreshold => self.on_quota_threshold.as_ref(),
+            HookEvent::QuotaReset => self.on_quota_reset.as_ref(),
+            HookEvent::NetworkLost => self.on_network_lost.as_ref(),
+        }
+        .filter(|command| !command.trim().is_empty())
+    }
+}
+
+/// Runs the command set for `event` on a thread of its own, so a slow hook
+/// never holds up logging in. Each detail is passed both as an
+/// `AUTOCAMPNET_<NAME>` environment variable and as a field of the JSON
+/// object written to stdin. What the command prints goes to `log_file`.
+pub fn fire(
+    settings: &HookSettings,
+    event: HookEvent,
+    details: &[(&str, String)],
+    log_file: PathBuf,
+) {
+    let command = match settings.command(event) {
+        Some(command) => command.to_string(),
+        None => return,
+    };
+    let mut fields: BTreeMap<String, String> = details
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    fields.insert("event".to_string(), event.name().to_string());
+    fields.insert("time".to_string(), chrono::Local::now().to_rfc3339());
+    let timeout = Duration::from_secs(settings.timeout_secs.max(1));
+    std::thread::spawn(move || {
+        let result = run(&command, &fields, timeout);
+        log(&log_file, event, &command, result).ok();
+    });
+}
+
+struct Finished {
+    status: String,
+    stdout: String,
+    stderr: String,
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        // A group of its own, so that a timeout also takes down whatever
+        // the command started
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+        shell
+    }
+}
+
+/// Kills the command along with everything it started
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+    #[cfg(windows)]
+    Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .output()
+        .ok();
+    child.kill().ok();
+    child.wait().ok();
+}
+
+/// Reads a pipe to the end on another thread. Commands that leave a child
+/// behind keep the pipe open, so the output is waited for only briefly.
+fn collect<R: Read + Send + 'static>(pipe: Option<R>) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    if let Some(mut pipe) = pipe {
+        std::thread::spawn(move || {
+            let mut output = Vec::new();
+            pipe.read_to_end(&mut output).ok();
+            sender
+                .send(String::from_utf8_lossy(&output).to_string())
+                .ok();
+        });
+    }
+    receiver
+}
+
+fn run(
+    command: &str,
+    fields: &BTreeMap<String, String>,
+    timeout: Duration,
+) -> Result<Finished, String> {
+    let mut child = shell(command)
+        .envs(
+            fields
+                .iter()
+                .map(|(name, value)| (format!("AUTOCAMPNET_{}", name.to_uppercase()), value)),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Commands that do not read stdin close it early, which is fine
+        stdin
+            .write_all(serde_json::to_string(fields).unwrap().as_bytes())
+            .ok();
+    }
+    let stdout = collect(child.stdout.take());
+    let stderr = collect(child.stderr.take());
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait().map_err(|err| err.to_string())? {
+            Some(status) => break status.to_string(),
+            None if started.elapsed() >= timeout => {
+                kill(&mut child);
+                break format!("killed after {} seconds", timeout.as_secs());
+            }
+            None => std::thread::sleep(Duration::from_millis(100)),
+        }
+    };
+    let wait = Duration::from_secs(1);
+    Ok(Finished {
+        status,
+        stdout: stdout.recv_timeout(wait).unwrap_or_default(),
+        stderr: stderr.recv_timeout(wait).unwrap_or_default(),
+    })
+}
+
+fn log(
+    log_file: &Path,
+    event: HookEvent,
+    command: &str,
+    result: Result<Finished, String>,
+) -> std::io::Result<()> {
+    if let Some(dir) = log_file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::metadata(log_file)
+        .map(|meta| meta.len())
+        .unwrap_or(0)
+        > LOG_LIMIT
+    {
+        std::fs::rename(log_file, log_file.with_extension("log.old"))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)?;
+    let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    match result {
+        Ok(finished) => {
+            writeln!(
+                file,
+                "[{}] {}: `{}` {}",
+                time,
+                event.name(),
+                command,
+                finished.status
+            )?;
+            for (name, output) in [("stdout", finished.stdout), ("stderr", finished.stderr)] {
+                for line in output.lines() {
+                    writeln!(file, "  {}: {}", name, line)?;
+                }
+            }
+        }
+        Err(err) => writeln!(
+            file,
+            "[{}] {}: `{}` could not be started: {}",
+            time,
+            event.name(),
+            command,
+            err
+        )?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::new();
+        fields.insert("event".to_string(), "login".to_string());
+        fields
+    }
+
+    #[test]
+    fn passes_details_in_the_environment_and_on_stdin() {
+        let command = if cfg!(windows) {
+            "echo %AUTOCAMPNET_EVENT%"
+        } else {
+            "echo $AUTOCAMPNET_EVENT; cat"
+        };
+        let finished = run(command, &fields(), Duration::from_secs(10)).unwrap();
+        assert!(finished.stdout.starts_with("login"));
+        if !cfg!(windows) {
+            assert!(finished.stdout.contains(r#"{"event":"login"}"#));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn timeout_kills_what_the_command_started() {
+        let started = Instant::now();
+        let finished = run(
+            "sleep 30 & echo $!; wait",
+            &fields(),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(finished.status, "killed after 1 seconds");
+        // The pipe closes with the whole group gone, rather than when the
+        // output wait runs out
+        assert!(started.elapsed() < Duration::from_millis(1900));
+        // The killed sleep may take a moment to exit, and stays a zombie
+        // where nothing reaps orphans
+        let stat = format!("/proc/{}/stat", finished.stdout.trim());
+        let gone = (0..50).any(|_| {
+            std::thread::sleep(Duration::from_millis(20));
+            let state = std::fs::read_to_string(&stat).unwrap_or_default();
+            state.is_empty() || state.contains(") Z ")
+        });
+        assert!(gone);
+    }
+}
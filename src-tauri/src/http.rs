@@ -0,0 +1,377 @@
+use reqwest::blocking::{Client, ClientBuilder};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::tls::{self, TlsFailure, TlsFailureSlot, TlsSettings};
+use crate::trust::TrustPolicy;
+
+#[derive(Serialize, Deserialize, Clone, TS)]
+#[serde(default)]
+pub struct HttpSettings {
+    #[ts(type = "number")]
+    pub connect_timeout_secs: u64,
+    /// Upper bound for a whole request, including reading the body
+    #[ts(type = "number")]
+    pub timeout_secs: u64,
+    /// How long an idle keep-alive connection is kept around for reuse
+    #[ts(type = "number")]
+    pub pool_idle_timeout_secs: u64,
+    pub cookie_store: bool,
+    /// Proxy for the connectivity probe and the user portal. The login
+    /// endpoint is a captive portal and is always reached directly when a
+    /// custom proxy is set.
+    pub proxy: ProxySetting,
+    /// Local address or interface name the login client connects from, for
+    /// machines that are on campus over only one of several interfaces
+    pub bind: Option<String>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            connect_timeout_secs: 5,
+            timeout_secs: 15,
+            pool_idle_timeout_secs: 90,
+            cookie_store: false,
+            proxy: ProxySetting::System,
+            bind: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "snake_case", tag = "mode", content = "url")]
+pub enum ProxySetting {
+    /// Follow the `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` variables
+    System,
+    /// Always connect directly, even if the environment sets a proxy
+    Direct,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy URL
+    Custom(String),
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct LocalInterface {
+    pub name: String,
+    #[ts(type = "string")]
+    pub address: IpAddr,
+    pub loopback: bool,
+}
+
+pub fn local_interfaces() -> Result<Vec<LocalInterface>, String> {
+    Ok(if_addrs::get_if_addrs()
+        .map_err(|e| format!("Could not list network interfaces: {}", e))?
+        .into_iter()
+        .map(|interface| LocalInterface {
+            address: interface.ip(),
+            loopback: interface.is_loopback(),
+            name: interface.name,
+        })
+        .collect())
+}
+
+/// Turns the `bind` setting into an address, looking it up as an interface
+/// name if it is not an address already. IPv4 is preferred since that is
+/// what the campus network hands out.
+fn bind_address(bind: &Option<String>) -> Result<Option<IpAddr>, String> {
+    if bind.is_none() {
+        return Ok(None);
+    }
+    let bind = bind.as_ref().unwrap().trim();
+    if let Ok(address) = IpAddr::from_str(bind) {
+        return Ok(Some(address));
+    }
+    let addresses: Vec<IpAddr> = local_interfaces()?
+        .into_iter()
+        .filter(|interface| interface.name == bind)
+        .map(|interface| interface.address)
+        .collect();
+    addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or_else(|| addresses.first())
+        .copied()
+        .map(Some)
+        .ok_or(format!("No address found on interface {}", bind))
+}
+
+#[derive(Clone)]
+struct Endpoint {
+    client: Client,
+    tls_failure: TlsFailureSlot,
+}
+
+/// Everything the clients are built from, kept for rebuilding them
+#[derive(Clone)]
+struct ClientSettings {
+    http: HttpSettings,
+    tls: TlsSettings,
+    trust: TrustPolicy,
+    version: String,
+}
+
+struct Clients {
+    general: Client,
+    login: Endpoint,
+    portal: Endpoint,
+    settings: ClientSettings,
+    /// What `bind` resolved to when the login client was built
+    login_address: Option<IpAddr>,
+}
+
+/// The clients every subsystem talks to the network through, so that
+/// timeouts apply everywhere and connections are reused between polls. The
+/// login endpoint and user portal get their own client each since they can
+/// be pinned to different certificates.
+pub struct HttpClient(Mutex<Clients>);
+
+impl HttpClient {
+    pub fn new(
+        http: &HttpSettings,
+        tls: &TlsSettings,
+        trust: &TrustPolicy,
+        version: &str,
+    ) -> Result<Self, String> {
+        Ok(HttpClient(Mutex::new(build_clients(ClientSettings {
+            http: http.clone(),
+            tls: tls.clone(),
+            trust: trust.clone(),
+            version: version.to_string(),
+        })?)))
+    }
+
+    /// Clients are reference counted internally, the clone shares the pool.
+    pub fn general(&self) -> Client {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .general
+            .clone()
+    }
+
+    /// The login client, rebuilt first when `bind` names an interface whose
+    /// address changed since, as it does with a new DHCP lease
+    pub fn login(&self) -> Client {
+        let mut clients = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        // An interface that is down keeps the old client, its requests fail
+        // the same way until the interface is back
+        let address = bind_address(&clients.settings.http.bind).unwrap_or(None);
+        if address.is_some() && address != clients.login_address {
+            if let Ok(rebuilt) = build_clients(clients.settings.clone()) {
+                *clients = rebuilt;
+            }
+        }
+        clients.login.client.clone()
+    }
+
+    pub fn portal(&self) -> Client {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .portal
+            .client
+            .clone()
+    }
+
+    /// Certificate problem behind the last failed handshake with the login
+    /// endpoint, if that is why it failed. Reading it leaves it in place for
+    /// other callers, it is only cleared by a handshake that goes through or
+    /// by rebuilding the clients.
+    pub fn login_tls_failure(&self) -> Option<TlsFailure> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .login
+            .tls_failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn portal_tls_failure(&self) -> Option<TlsFailure> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .portal
+            .tls_failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn rebuild(
+        &self,
+        http: &HttpSettings,
+        tls: &TlsSettings,
+        trust: &TrustPolicy,
+        version: &str,
+    ) -> Result<(), String> {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = build_clients(ClientSettings {
+            http: http.clone(),
+            tls: tls.clone(),
+            trust: trust.clone(),
+            version: version.to_string(),
+        })?;
+        Ok(())
+    }
+}
+
+fn build_clients(settings: ClientSettings) -> Result<Clients, String> {
+    let ClientSettings {
+        http,
+        tls,
+        trust,
+        version,
+    } = &settings;
+    let roots = tls::root_store(tls)?;
+    let login_address = bind_address(&http.bind)?;
+    let login_proxy = match &http.proxy {
+        ProxySetting::Custom(_) => ProxySetting::Direct,
+        proxy => proxy.clone(),
+    };
+    Ok(Clients {
+        general: build_client(
+            http,
+            version,
+            tls::client_config(roots.clone(), "general", None, None, Arc::default()),
+            &http.proxy,
+            None,
+        )?,
+        login: build_endpoint(
+            http,
+            version,
+            |failure| {
+                tls::client_config(
+                    roots.clone(),
+                    "login endpoint",
+                    tls.login_pin.clone(),
+                    trust.certificate_fingerprint.as_deref(),
+                    failure,
+                )
+            },
+            &login_proxy,
+            login_address,
+        )?,
+        portal: build_endpoint(
+            http,
+            version,
+            |failure| {
+                tls::client_config(
+                    roots.clone(),
+                    "user portal",
+                    tls.portal_pin.clone(),
+                    None,
+                    failure,
+                )
+            },
+            &http.proxy,
+            None,
+        )?,
+        settings,
+        login_address,
+    })
+}
+
+/// `tls_config` gets the slot its verifier reports handshake failures to
+fn build_endpoint(
+    http: &HttpSettings,
+    version: &str,
+    tls_config: impl FnOnce(TlsFailureSlot) -> rustls::ClientConfig,
+    proxy: &ProxySetting,
+    local_address: Option<IpAddr>,
+) -> Result<Endpoint, String> {
+    let tls_failure: TlsFailureSlot = Arc::default();
+    let tls_config = tls_config(tls_failure.clone());
+    Ok(Endpoint {
+        client: build_client(http, version, tls_config, proxy, local_address)?,
+        tls_failure,
+    })
+}
+
+fn build_client(
+    settings: &HttpSettings,
+    version: &str,
+    tls_config: rustls::ClientConfig,
+    proxy: &ProxySetting,
+    local_address: Option<IpAddr>,
+) -> Result<Client, String> {
+    let builder = ClientBuilder::new()
+        .use_preconfigured_tls(tls_config)
+        .user_agent(format!("AutoCampnetRuntime/{}", version))
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .timeout(Duration::from_secs(settings.timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(settings.pool_idle_timeout_secs))
+        .tcp_keepalive(Duration::from_secs(60))
+        .cookie_store(settings.cookie_store)
+        .local_address(local_address);
+    let builder = match proxy {
+        ProxySetting::System => builder,
+        ProxySetting::Direct => builder.no_proxy(),
+        ProxySetting::Custom(url) => builder
+            .proxy(reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy {}: {}", url, e))?),
+    };
+    builder.build().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_a_tls_failure_leaves_it_for_other_callers() {
+        let client = HttpClient::new(
+            &HttpSettings::default(),
+            &TlsSettings::default(),
+            &TrustPolicy::default(),
+            "test",
+        )
+        .unwrap();
+        *client.0.lock().unwrap().login.tls_failure.lock().unwrap() = Some(TlsFailure {
+            endpoint: "login endpoint".to_string(),
+            reason: "certificate does not match the configured pin".to_string(),
+            served_pin: None,
+        });
+        assert!(client.login_tls_failure().is_some());
+        assert!(client.login_tls_failure().is_some());
+        assert!(client.portal_tls_failure().is_none());
+        // A rebuilt client starts over
+        client
+            .rebuild(
+                &HttpSettings::default(),
+                &TlsSettings::default(),
+                &TrustPolicy::default(),
+                "test",
+            )
+            .unwrap();
+        assert!(client.login_tls_failure().is_none());
+    }
+
+    #[test]
+    fn clients_outlive_a_panic_while_locked() {
+        let client = Arc::new(
+            HttpClient::new(
+                &HttpSettings::default(),
+                &TlsSettings::default(),
+                &TrustPolicy::default(),
+                "test",
+            )
+            .unwrap(),
+        );
+        let panicking = client.clone();
+        std::thread::spawn(move || {
+            let _clients = panicking.0.lock().unwrap();
+            panic!("poisons the lock");
+        })
+        .join()
+        .ok();
+        assert!(client.0.is_poisoned());
+        client.general();
+        client.login();
+        assert!(client.login_tls_failure().is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::account::AccountDetails;
+use crate::quota::Quota;
+use crate::tls::TlsFailure;
+
+/// Saved credentials as the window sees them, without the password
+#[derive(Serialize, Clone, TS)]
+#[ts(rename = "Credentials")]
+pub struct CredentialsView {
+    pub username: String,
+    pub has_password: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, TS)]
+#[ts(rename = "Traffic")]
+pub struct TrafficStats {
+    pub total: f32,
+    pub last: f32,
+    pub current: f32,
+    pub used: f32,
+    pub remaining: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, TS)]
+pub struct TrafficUnits {
+    pub total: String,
+    pub last: String,
+    pub current: String,
+    pub used: String,
+    pub remaining: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Copy, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Disconnected,
+    Connected,
+    Idle,
+    CertificateError,
+    OutsideSchedule,
+}
+
+impl ConnectionState {
+    /// Name as serialised, used for MQTT and the status file
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionState::Disconnected => "disconnected",
+            ConnectionState::Connected => "connected",
+            ConnectionState::Idle => "idle",
+            ConnectionState::CertificateError => "certificate_error",
+            ConnectionState::OutsideSchedule => "outside_schedule",
+        }
+    }
+}
+
+/// The next change of the login schedule, `at` being unix milliseconds
+#[derive(Serialize, Clone, Copy, PartialEq, TS)]
+pub struct ScheduleTransition {
+    #[ts(type = "number")]
+    pub at: i64,
+    /// Whether logging in is allowed from then on
+    pub allowed: bool,
+}
+
+/// What checking credentials found out about the account, so the window can
+/// warn when the password is right but there is no quota left to log in with
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct CredentialCheck {
+    /// The quota closest to running out, when the user portal reported one
+    pub quota: Option<Quota>,
+    pub quota_exhausted: bool,
+}
+
+/// Why credentials were not checked or saved
+#[derive(Serialize, Clone, Copy, PartialEq, TS)]
+#[serde(rename_all = "UPPERCASE")]
+#[ts(export)]
+pub enum CredentialError {
+    /// No password was entered and none is saved for the username
+    NoPassword,
+    Paused,
+    /// Outside the login windows of the schedule
+    Schedule,
+    Untrusted,
+    InvalidCred,
+    Certificate,
+    /// The captive portal could not be reached
+    NoSophos,
+    /// The credentials file could not be written
+    NotSaved,
+    /// The user portal could not confirm the credentials and no captive
+    /// portal login was asked for, which would disturb live sessions
+    Unverifiable,
+    Unknown,
+}
+
+/// Everything the window shows, for filling it in when it opens
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct AppStatus {
+    pub credentials: CredentialsView,
+    pub traffic: TrafficStats,
+    pub traffic_units: TrafficUnits,
+    pub quotas: Vec<Quota>,
+    pub account_details: Option<AccountDetails>,
+    pub autolaunch: bool,
+    /// Unix milliseconds of the next login attempt
+    #[ts(type = "number | null")]
+    pub next_retry: Option<i64>,
+    pub connection_state: ConnectionState,
+    pub schedule_transition: Option<ScheduleTransition>,
+    /// Why the login endpoint or user portal was refused, while that is
+    /// what keeps the app from connecting
+    pub tls_failure: Option<TlsFailure>,
+}
+
+/// Events sent to the window, the tag being the event name. The frontend
+/// listens through the generated type, so a payload cannot change on one
+/// side only.
+#[derive(Serialize, Clone, TS)]
+#[serde(rename_all = "snake_case", tag = "event", content = "payload")]
+#[ts(export)]
+pub enum AppEvent {
+    Credentials(CredentialsView),
+    Traffic(TrafficStats),
+    TrafficUnits(TrafficUnits),
+    Quotas(Vec<Quota>),
+    AccountDetails(Option<AccountDetails>),
+    Autolaunch(bool),
+    NextRetry(#[ts(type = "number | null")] Option<i64>),
+    ConnectionState(ConnectionState),
+    ScheduleTransition(Option<ScheduleTransition>),
+    TlsFailure(TlsFailure),
+    /// The portal refused a login because of the session limit
+    SessionLimit,
+}
+
+impl AppEvent {
+    /// Event name and payload as passed to `Window::emit`
+    pub fn split(&self) -> serde_json::Result<(String, serde_json::Value)> {
+        let mut event = serde_json::to_value(self)?;
+        let name = event["event"].as_str().unwrap_or_default().to_string();
+        Ok((name, event["payload"].take()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_names_match_their_serialised_form() {
+        for state in [
+            ConnectionState::Disconnected,
+            ConnectionState::Connected,
+            ConnectionState::Idle,
+            ConnectionState::CertificateError,
+            ConnectionState::OutsideSchedule,
+        ] {
+            assert_eq!(serde_json::json!(state.as_str()), serde_json::json!(state));
+        }
+    }
+
+    #[test]
+    fn events_split_into_name_and_payload() {
+        let (name, payload) = AppEvent::NextRetry(Some(1_000)).split().unwrap();
+        assert_eq!(name, "next_retry");
+        assert_eq!(payload, serde_json::json!(1_000));
+    }
+}
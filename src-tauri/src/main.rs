@@ -4,44 +4,78 @@
     windows_subsystem = "windows"
 )]
 
-use auto_launch::{AutoLaunch, AutoLaunchBuilder, Error};
-use regex::Regex;
+use auto_launch::{AutoLaunch, AutoLaunchBuilder};
 use serde::{Deserialize, Serialize};
 use std::env::current_exe;
 use std::io::Write;
-use std::sync::{Arc, Mutex};
-use tauri::{
-    api::{file, notification::Notification},
-    Manager, State,
-};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tauri::{api::file, ClipboardManager, Manager, State};
 extern crate chrono;
 extern crate timer;
 
+mod account;
+mod captive;
+mod error;
+#[cfg(test)]
+mod fixture_server;
+mod hooks;
+mod http;
+mod ipc;
+mod mqtt;
+mod portal;
+mod quota;
+mod retry;
+mod schedule;
+mod secret;
+mod sessions;
+mod settings;
+mod status_file;
+mod tls;
+mod tray_icon;
+mod trust;
+mod ui;
+mod webhooks;
+
+use account::AccountDetails;
+use captive::{CampusProfile, CaptivePortal, LoginOutcome, Vendor};
+use error::{AppError, AppResult, OrReport};
+use hooks::HookEvent;
+use http::{HttpClient, HttpSettings, ProxySetting};
+use ipc::{
+    AppEvent, AppStatus, ConnectionState, CredentialCheck, CredentialError, CredentialsView,
+    ScheduleTransition, TrafficStats, TrafficUnits,
+};
+use mqtt::{Mqtt, MqttCommand};
+use portal::{PortalBackend, PortalError, PortalSession};
+use quota::Quota;
+use retry::LoginFailure;
+use secret::Secret;
+use settings::Settings;
+use tls::{TlsFailure, TlsSettings};
+use tray_icon::{IconKey, TrayIcon};
+use webhooks::Webhooks;
+
+/// Seconds between usage updates while connected
+const USAGE_INTERVAL_SECS: i64 = 45;
+
+/// Saved credentials, both URL encoded. Only `view` is sent to the window.
 #[derive(Serialize, Deserialize, Clone)]
 struct Credentials {
     username: String,
-    password: String,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-struct TrafficStats {
-    total: f32,
-    last: f32,
-    current: f32,
-    used: f32,
-    remaining: f32,
+    password: Secret,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct TrafficUnits {
-    total: String,
-    last: String,
-    current: String,
-    used: String,
-    remaining: String,
+impl Credentials {
+    fn view(&self) -> CredentialsView {
+        CredentialsView {
+            username: self.username.to_string(),
+            has_password: !self.password.is_empty(),
+        }
+    }
 }
 
-#[derive(Clone, PartialEq, Copy)]
+/// Ordered by how much of the quota is used
+#[derive(Clone, PartialEq, PartialOrd, Copy)]
 enum NotificationState {
     None,
     Used50,
@@ -49,182 +83,821 @@ enum NotificationState {
     Used100,
 }
 
+/// Auto-login is held off until a time, until resumed from the tray or
+/// until the app restarts
+#[derive(Clone, Copy, PartialEq)]
+enum Pause {
+    Until(chrono::DateTime<chrono::Local>),
+    Indefinitely,
+    UntilRestart,
+}
+
+/// A pause as kept on disk, `until` being unix milliseconds or missing for
+/// an indefinite pause
+#[derive(Serialize, Deserialize)]
+struct SavedPause {
+    until: Option<i64>,
+}
+
 #[derive(Clone)]
 struct AppState {
     login_endpoint: String,
     credentials: Credentials,
     login_guard: Option<timer::Guard>,
+    /// Dropping a timer stops its thread, so the one behind `login_guard` is
+    /// kept until the next round replaces it
+    login_timer: Option<timer::Timer>,
     portal_endpoint: String,
-    cookie: String,
-    csrf: String,
+    portal_backend: Option<Arc<dyn PortalBackend>>,
+    portal_session: Option<PortalSession>,
+    /// Session a credential check logged in with and the username it is
+    /// for, taken over when those credentials are saved
+    checked_session: Option<(String, PortalSession)>,
     traffic: TrafficStats,
     traffic_units: TrafficUnits,
     traffic_guard: Option<timer::Guard>,
+    traffic_timer: Option<timer::Timer>,
     last_notification_state: NotificationState,
+    connection_state: ConnectionState,
+    login_attempts: u32,
+    /// Set once the retries for a failure are used up, until a login goes
+    /// through or the credentials change. The loop then only probes slowly
+    /// and stays quiet.
+    gave_up: Option<LoginFailure>,
+    probe_failures: u32,
+    next_retry: Option<chrono::DateTime<chrono::Local>>,
+    settings: Settings,
+    account_details: Option<AccountDetails>,
+    quotas: Vec<Quota>,
+    captive: Arc<dyn CaptivePortal>,
+    last_keepalive: Option<chrono::DateTime<chrono::Local>>,
+    pause: Option<Pause>,
+    last_update: Option<chrono::DateTime<chrono::Local>>,
+    schedule_transition: Option<ScheduleTransition>,
 }
 
-pub struct AutoLaunchManager(AutoLaunch);
+/// Locks the app state, going on with it even if a panicking thread held
+/// the lock. Every round leaves the state usable between its steps, and a
+/// poisoned lock would otherwise take every command and loop down with it.
+fn lock_state(app_state: &Mutex<AppState>) -> MutexGuard<'_, AppState> {
+    app_state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Missing when the app could not work out its own path at startup
+pub struct AutoLaunchManager(Option<AutoLaunch>);
 
 impl AutoLaunchManager {
-    pub fn enable(&self) -> Result<(), Error> {
-        self.0.enable()
+    fn auto_launch(&self) -> AppResult<&AutoLaunch> {
+        self.0
+            .as_ref()
+            .ok_or_else(|| AppError::Other("Start at boot is not available".to_string()))
+    }
+
+    pub fn enable(&self) -> AppResult<()> {
+        Ok(self.auto_launch()?.enable()?)
     }
 
-    pub fn disable(&self) -> Result<(), Error> {
-        self.0.disable()
+    pub fn disable(&self) -> AppResult<()> {
+        Ok(self.auto_launch()?.disable()?)
     }
 
-    pub fn is_enabled(&self) -> Result<bool, Error> {
-        self.0.is_enabled()
+    pub fn is_enabled(&self) -> AppResult<bool> {
+        Ok(self.auto_launch()?.is_enabled()?)
     }
 }
 
-fn save_creds(creds: Credentials, save_file: &std::path::Path) {
-    let mut file = std::fs::File::create(&save_file).unwrap();
-    write!(&mut file, "{}", serde_json::to_string(&creds).unwrap()).unwrap();
+/// Path of `name` in the app's config directory
+fn config_file(app: &tauri::AppHandle, name: &str) -> AppResult<std::path::PathBuf> {
+    app.path_resolver()
+        .app_config_dir()
+        .map(|config_dir| config_dir.join(name))
+        .ok_or(AppError::NoPath("config"))
+}
+
+/// Writes to a temporary file first so that a crash halfway through never
+/// leaves the credentials file truncated.
+fn save_creds(creds: Credentials, save_file: &std::path::Path) -> AppResult<()> {
+    let temp_file = save_file.with_extension("json.tmp");
+    let mut file = std::fs::File::create(&temp_file)?;
+    write!(&mut file, "{}", serde_json::to_string(&creds)?)?;
+    file.sync_all()?;
+    std::fs::rename(&temp_file, save_file)?;
+    Ok(())
 }
 
 fn load_creds(save_file: &std::path::Path) -> Result<Credentials, String> {
     let creds_string = file::read_string(save_file);
     if creds_string.is_ok() {
-        let creds: Credentials = serde_json::from_str(&creds_string.unwrap()).unwrap();
-        return Ok(creds);
+        // A damaged file is treated as no credentials, which asks for them
+        // again
+        return serde_json::from_str(&creds_string.unwrap())
+            .map_err(|_| "Credentials file is damaged".to_string());
     } else {
         return Err("Credentials not saved".to_string());
     }
 }
 
-fn login_campnet(
-    client: reqwest::blocking::Client,
-    creds: Credentials,
-    login_endpoint: String,
-) -> Result<reqwest::blocking::Response, reqwest::Error> {
-    let body: String = format!(
-        "mode=191&username={}&password={}&a={}&producttype=1",
-        creds.username,
-        creds.password,
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
+/// Pauses until restart are not saved, so the file is removed for them
+fn save_pause(pause: Option<Pause>, save_file: &std::path::Path) -> AppResult<()> {
+    let saved = match pause {
+        Option::Some(Pause::Until(until)) => Option::Some(SavedPause {
+            until: Option::Some(until.timestamp_millis()),
+        }),
+        Option::Some(Pause::Indefinitely) => Option::Some(SavedPause {
+            until: Option::None,
+        }),
+        Option::Some(Pause::UntilRestart) | Option::None => Option::None,
+    };
+    if saved.is_some() {
+        let mut file = std::fs::File::create(save_file)?;
+        write!(&mut file, "{}", serde_json::to_string(&saved.unwrap())?)?;
+    } else if save_file.exists() {
+        std::fs::remove_file(save_file)?;
+    }
+    Ok(())
+}
+
+fn load_pause(save_file: &std::path::Path) -> Option<Pause> {
+    let pause_string = file::read_string(save_file);
+    if pause_string.is_err() {
+        return Option::None;
+    }
+    let saved = serde_json::from_str::<SavedPause>(&pause_string.unwrap()).ok()?;
+    match saved.until {
+        // A pause that ran out while the app was closed is simply dropped
+        Option::Some(until) => chrono::TimeZone::timestamp_millis_opt(&chrono::Local, until)
+            .single()
+            .filter(|until| *until > chrono::Local::now())
+            .map(Pause::Until),
+        Option::None => Option::Some(Pause::Indefinitely),
+    }
+}
+
+fn general_client(app: &tauri::AppHandle) -> reqwest::blocking::Client {
+    app.state::<HttpClient>().general()
+}
+
+fn login_client(app: &tauri::AppHandle) -> reqwest::blocking::Client {
+    app.state::<HttpClient>().login()
+}
+
+fn portal_client(app: &tauri::AppHandle) -> reqwest::blocking::Client {
+    app.state::<HttpClient>().portal()
+}
+
+/// Hands `event` to the user's hook script and webhooks, adding the
+/// username to `details`
+fn fire_event(app: &tauri::AppHandle, event: HookEvent, details: &[(&str, String)]) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let settings = lock_state(&app_state).settings.clone();
+    let username = lock_state(&app_state).credentials.username.to_string();
+    let mut details = details.to_vec();
+    details.push((
+        "username",
+        percent_encoding::percent_decode_str(&username)
+            .decode_utf8_lossy()
+            .to_string(),
+    ));
+    hooks::fire(
+        &settings.hooks,
+        event,
+        &details,
+        app.path_resolver()
+            .app_log_dir()
+            .unwrap_or_default()
+            .join("hooks.log"),
+    );
+    app.state::<Webhooks>()
+        .enqueue(&settings.webhooks, event, &details);
+}
+
+/// Sends `event` to the window under its name
+fn emit_event(app: &tauri::AppHandle, event: AppEvent) {
+    event
+        .split()
+        .map_err(AppError::from)
+        .and_then(|(name, payload)| Ok(ui::main_window(app)?.emit(&name, payload)?))
+        .or_report("Window event");
+}
+
+fn set_connection_state(app: &tauri::AppHandle, state: ConnectionState) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    if lock_state(&app_state).connection_state != state {
+        lock_state(&app_state).connection_state = state;
+        emit_event(app, AppEvent::ConnectionState(state));
+        publish_connection_state(app);
+        refresh_tray_icon(app);
+        refresh_tray_menu(app);
+    }
+}
+
+fn publish_connection_state(app: &tauri::AppHandle) {
+    let state = lock_state(&app.state::<Arc<Mutex<AppState>>>()).connection_state;
+    app.state::<Mqtt>().publish_state(state.as_str());
+}
+
+/// Connects to the MQTT broker from settings, replacing the old connection
+fn start_mqtt(app: &tauri::AppHandle) {
+    let settings = lock_state(&app.state::<Arc<Mutex<AppState>>>())
+        .settings
+        .mqtt
+        .clone();
+    let app_handle_command = app.app_handle();
+    app.state::<Mqtt>().start(
+        &settings,
+        Arc::new(move |command| mqtt_command(&app_handle_command, command)),
+    );
+}
+
+/// Commands from MQTT do what the matching tray item does. Logging in lifts
+/// a pause first, since reconnecting alone would not get past it.
+fn mqtt_command(app: &tauri::AppHandle, command: MqttCommand) {
+    match command {
+        MqttCommand::Login => {
+            let paused = lock_state(&app.state::<Arc<Mutex<AppState>>>())
+                .pause
+                .is_some();
+            tray_action(app, if paused { "resume" } else { "reconnect" });
+        }
+        MqttCommand::Logout => tray_action(app, "logout"),
+        MqttCommand::Reconnect => tray_action(app, "reconnect"),
+    }
+}
+
+/// Whether auto-login is paused, lifting a pause whose time is up. Every
+/// path that logs in checks this first.
+fn auto_login_paused(app: &tauri::AppHandle) -> bool {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let pause = lock_state(&app_state).pause;
+    match pause {
+        Option::Some(Pause::Until(until)) => !resume_when_due(app, until),
+        Option::Some(_) => true,
+        Option::None => false,
+    }
+}
+
+/// Sets or lifts the pause and saves it. A timed pause gets a thread of its
+/// own that lifts it, since the login loop is not running after a logout.
+fn pause_auto_login(app: &tauri::AppHandle, pause: Option<Pause>) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    lock_state(&app_state).pause = pause;
+    config_file(app, "pause.json")
+        .and_then(|save_file| save_pause(pause, &save_file))
+        .or_report("Saving pause");
+    app.tray_handle()
+        .get_item("resume")
+        .set_enabled(pause.is_some())
+        .or_report("Tray");
+    refresh_tray_menu(app);
+    if let Option::Some(Pause::Until(until)) = pause {
+        let app_handle_resume = app.app_handle();
+        std::thread::spawn(move || {
+            let wait = (until - chrono::Local::now())
+                .to_std()
+                .unwrap_or(std::time::Duration::ZERO);
+            std::thread::sleep(wait);
+            if resume_when_due(&app_handle_resume, until) {
+                restart_login_loop(&app_handle_resume);
+            }
+        });
+    }
+}
+
+/// Lifts the pause ending at `until` if it is still the one set and its
+/// time is up. Returns whether auto-login is on.
+fn resume_when_due(app: &tauri::AppHandle, until: chrono::DateTime<chrono::Local>) -> bool {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let pause = lock_state(&app_state).pause;
+    if pause == Option::Some(Pause::Until(until)) && until <= chrono::Local::now() {
+        pause_auto_login(app, Option::None);
+        ui::notify(
+            "Auto-login resumed",
+            "App will try to login to campnet whenever available",
+        );
+        true
+    } else {
+        pause.is_none()
+    }
+}
+
+/// Starts the login loop again if nothing is scheduled, as after a logout
+fn restart_login_loop(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let creds = lock_state(&app_state).credentials.to_owned();
+    if lock_state(&app_state).login_guard.is_none() && !creds.username.is_empty() {
+        reset_retries(app);
+        let app_handle_thread = app.app_handle();
+        std::thread::spawn(move || {
+            supervised_round(&app_handle_thread, "Login loop", connect_campnet);
+        });
+    }
+}
+
+/// Connection state, remaining quota and time of the last usage update, as
+/// shown in the tray menu and tooltip
+fn status_lines(app: &tauri::AppHandle) -> Vec<String> {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let state = lock_state(&app_state).connection_state;
+    let pause = lock_state(&app_state).pause;
+    let status = match (pause, state) {
+        (Option::Some(Pause::Until(until)), _) => {
+            format!("Auto-login paused until {}", until.format("%H:%M"))
+        }
+        (Option::Some(Pause::Indefinitely), _) => "Auto-login paused".to_string(),
+        (Option::Some(Pause::UntilRestart), _) => "Auto-login paused until restart".to_string(),
+        (Option::None, ConnectionState::Connected) => "Connected".to_string(),
+        (Option::None, ConnectionState::Disconnected) => "Disconnected".to_string(),
+        (Option::None, ConnectionState::Idle) => "Not logging in on this network".to_string(),
+        (Option::None, ConnectionState::CertificateError) => "Certificate problem".to_string(),
+        (Option::None, ConnectionState::OutsideSchedule) => "Outside login hours".to_string(),
+    };
+    let quotas = lock_state(&app_state).quotas.clone();
+    let remaining = match quota::most_constrained(&quotas) {
+        Option::Some(constrained) => format!(
+            "Remaining: {} of {}",
+            constrained.format_amount(constrained.remaining),
+            constrained.format_amount(constrained.limit)
+        ),
+        Option::None => "Remaining: unknown".to_string(),
+    };
+    let updated = match lock_state(&app_state).last_update {
+        Option::Some(last_update) => format!("Updated: {}", last_update.format("%H:%M:%S")),
+        Option::None => "Updated: never".to_string(),
+    };
+    let schedule = match lock_state(&app_state).schedule_transition {
+        Option::Some(transition) => {
+            let at = chrono::TimeZone::timestamp_millis_opt(&chrono::Local, transition.at)
+                .single()
+                .map(|at| at.format("%a %H:%M").to_string())
+                .unwrap_or_default();
+            if transition.allowed {
+                format!("Login hours start {}", at)
+            } else {
+                format!("Login hours end {}", at)
+            }
+        }
+        Option::None => "Login hours: always".to_string(),
+    };
+    vec![status, remaining, updated, schedule]
+}
+
+/// Updates the status file read by status bars
+fn write_status_file(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let settings = lock_state(&app_state).settings.status_file.clone();
+    if !settings.enabled {
+        return;
+    }
+    let state = lock_state(&app_state).connection_state;
+    let quotas = lock_state(&app_state).quotas.clone();
+    let constrained = quota::most_constrained(&quotas);
+    let mut status = status_file::Status {
+        state: state.as_str().to_string(),
+        status: status_lines(app)[0].to_string(),
+        used: constrained.map(|constrained| constrained.used),
+        remaining: constrained.map(|constrained| constrained.remaining),
+        limit: constrained.map(|constrained| constrained.limit),
+        unit: constrained.map(|constrained| constrained.unit.to_string()),
+        remaining_text: constrained
+            .map(|constrained| constrained.format_amount(constrained.remaining)),
+        percentage: constrained
+            .map(|constrained| (constrained.usage() * 100.0).round().clamp(0.0, 100.0) as u8),
+        next_retry: lock_state(&app_state)
+            .next_retry
+            .map(|next_retry| next_retry.timestamp_millis()),
+        updated: chrono::Local::now().timestamp_millis(),
+        stale_after: 0,
+        text: "".to_string(),
+    };
+    // The longest the app goes without writing again is the longest wait
+    // of either loop
+    let retry = lock_state(&app_state).settings.retry.clone();
+    let longest_wait = retry
+        .max_delay_secs
+        .max(retry.invalid_credentials_delay_secs)
+        .max(USAGE_INTERVAL_SECS as u64);
+    status.stale_after = status_file::stale_after(status.updated, longest_wait, retry.jitter);
+    status_file::write(&settings, &mut status).or_report("Status file");
+}
+
+fn refresh_tray_menu(app: &tauri::AppHandle) {
+    let lines = status_lines(app);
+    let tray_handle = app.tray_handle();
+    for (id, line) in ["status", "remaining", "updated", "schedule"]
+        .iter()
+        .zip(&lines)
+    {
+        tray_handle.get_item(id).set_title(line).or_report("Tray");
+    }
+    tray_handle
+        .set_tooltip(&format!("Auto Campnet\n{}", lines.join("\n")))
+        .or_report("Tray");
+}
+
+/// Redraws the tray icon from the connection state and the most constrained
+/// quota. The bundled icons are used when drawing is turned off or fails.
+fn refresh_tray_icon(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let state = lock_state(&app_state).connection_state;
+    let quotas = lock_state(&app_state).quotas.clone();
+    let key = IconKey {
+        state,
+        used: quota::most_constrained(&quotas)
+            .map(|constrained| (constrained.usage() * 100.0).round().clamp(0.0, 100.0) as u8),
+        settings: lock_state(&app_state).settings.tray_icon.clone(),
+    };
+    let tray_icon = app.state::<TrayIcon>();
+    if !tray_icon.changed(&key) {
+        return;
+    }
+    let rendered = if key.settings.dynamic {
+        tray_icon.render(&key)
+    } else {
+        Err("Dynamic tray icon is turned off".to_string())
+    };
+    if rendered.is_ok() {
+        app.tray_handle()
+            .set_icon(tauri::Icon::Rgba {
+                rgba: rendered.unwrap(),
+                width: tray_icon::SIZE,
+                height: tray_icon::SIZE,
+            })
+            .or_report("Tray icon");
+    } else {
+        let icon = if state != ConnectionState::Connected {
+            "resources/icons/inactive.png"
+        } else {
+            match lock_state(&app_state).last_notification_state {
+                NotificationState::None => "resources/icons/active.png",
+                NotificationState::Used50 => "resources/icons/used_50.png",
+                NotificationState::Used90 => "resources/icons/used_90.png",
+                NotificationState::Used100 => "resources/icons/inactive.png",
+            }
+        };
+        ui::set_tray_icon_file(app, icon);
+    }
+}
+
+/// Runs the trust policy from settings against the current network, moving
+/// the app to idle and telling the user once when the network is rejected.
+fn network_trusted(app: &tauri::AppHandle) -> bool {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let policy = lock_state(&app_state).settings.trust.clone();
+    let check = trust::check_network(&policy);
+    if check.is_err() {
+        if lock_state(&app_state).connection_state != ConnectionState::Idle {
+            ui::notify("Not logging in to Campnet", check.err().unwrap().describe());
+        }
+        set_connection_state(app, ConnectionState::Idle);
+        false
+    } else {
+        true
+    }
+}
+
+fn set_schedule_transition(app: &tauri::AppHandle, transition: Option<ScheduleTransition>) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    if lock_state(&app_state).schedule_transition != transition {
+        lock_state(&app_state).schedule_transition = transition;
+        emit_event(app, AppEvent::ScheduleTransition(transition));
+        refresh_tray_menu(app);
+    }
+}
+
+/// Checks the login schedule from settings. The first check outside the
+/// login hours logs out, after which the app waits for the next window.
+fn within_login_window(app: &tauri::AppHandle) -> bool {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let schedule = lock_state(&app_state).settings.schedule.clone();
+    let now = chrono::Local::now().naive_local();
+    let allowed = schedule.allows(now);
+    let next = schedule
+        .next_transition(now)
+        .and_then(|next| chrono::TimeZone::from_local_datetime(&chrono::Local, &next).earliest())
+        .map(|next| ScheduleTransition {
+            at: next.timestamp_millis(),
+            allowed: !allowed,
+        });
+    set_schedule_transition(app, next);
+    if !allowed && lock_state(&app_state).connection_state != ConnectionState::OutsideSchedule {
+        // Only a campnet session is logged out, the username is never sent
+        // to whatever answers at the login host off campus or on a network
+        // that is not trusted
+        let was_connected = lock_state(&app_state).connection_state == ConnectionState::Connected;
+        let policy = lock_state(&app_state).settings.trust.clone();
+        let client = login_client(app);
+        let captive = captive_portal(app);
+        if was_connected && trust::check_network(&policy).is_ok() && captive.detect(&client) {
+            let username = lock_state(&app_state).credentials.username.to_string();
+            let logged_out = captive.logout(&client, &username).is_ok();
+            let at = next.and_then(|next| {
+                chrono::TimeZone::timestamp_millis_opt(&chrono::Local, next.at).single()
+            });
+            let body = match at {
+                _ if !logged_out => "Could not log out of campnet".to_string(),
+                Option::Some(at) => format!(
+                    "Logged out of campnet, logging in again at {}",
+                    at.format("%a %H:%M")
+                ),
+                Option::None => "Logged out of campnet".to_string(),
+            };
+            ui::notify("Outside login hours", body);
+            if logged_out {
+                fire_event(
+                    app,
+                    HookEvent::Logout,
+                    &[("reason", "schedule".to_string())],
+                );
+            }
+        }
+        set_connection_state(app, ConnectionState::OutsideSchedule);
+    }
+    allowed
+}
+
+fn captive_portal(app: &tauri::AppHandle) -> Arc<dyn CaptivePortal> {
+    lock_state(&app.state::<Arc<Mutex<AppState>>>())
+        .captive
+        .clone()
+}
+
+/// Points the app at the campus from settings. The user portal is detected
+/// again since the new campus may run a different version.
+fn apply_campus(
+    app: &tauri::AppHandle,
+    campus: &CampusProfile,
+    captive_portal: Arc<dyn CaptivePortal>,
+) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    lock_state(&app_state).login_endpoint = campus.login_endpoint.to_string();
+    lock_state(&app_state).portal_endpoint = campus.portal_endpoint.to_string();
+    lock_state(&app_state).captive = captive_portal;
+    lock_state(&app_state).portal_backend = Option::None;
+    clear_portal_session(app);
+}
+
+/// Keeps the current login alive, at most once a minute since the status
+/// check runs far more often than portals time out.
+fn keep_alive(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let last_keepalive = lock_state(&app_state).last_keepalive;
+    if last_keepalive.is_none()
+        || chrono::Local::now() - last_keepalive.unwrap() > chrono::Duration::seconds(60)
+    {
+        let username = lock_state(&app_state).credentials.username.to_string();
+        captive_portal(app)
+            .keepalive(&login_client(app), &username)
+            .ok();
+        lock_state(&app_state).last_keepalive = Option::Some(chrono::Local::now());
+    }
+}
+
+/// Runs one round of a background loop, running it again after a panic
+/// instead of letting the loop stop for the rest of the session. A lock the
+/// round held when it panicked is usable again once it restarts.
+fn supervised_round(app: &tauri::AppHandle, name: &str, round: fn(tauri::AppHandle, bool)) {
+    error::supervise(
+        name,
+        || round(app.app_handle(), false),
+        || app.state::<Arc<Mutex<AppState>>>().clear_poison(),
     );
-    return client
-        .post(login_endpoint.to_owned() + "/login.xml")
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .header("Content-Length", body.chars().count())
-        .body(body)
-        .send();
+}
+
+/// Writes the status file and schedules the next round of the login loop.
+/// The status goes out first so that it never overwrites what a round that
+/// already started wrote.
+fn schedule_login(app: &tauri::AppHandle, delay: chrono::Duration) {
+    write_status_file(app);
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let app_handle_next = app.app_handle();
+    let callback_timer = timer::Timer::new();
+    let callback_gaurd = callback_timer.schedule_with_delay(delay, move || {
+        supervised_round(&app_handle_next, "Login loop", connect_campnet);
+    });
+    lock_state(&app_state).login_guard = Option::Some(callback_gaurd);
+    lock_state(&app_state).login_timer = Option::Some(callback_timer);
+}
+
+fn set_next_retry(app: &tauri::AppHandle, next_retry: Option<chrono::DateTime<chrono::Local>>) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    if lock_state(&app_state).next_retry != next_retry {
+        lock_state(&app_state).next_retry = next_retry;
+        emit_event(
+            app,
+            AppEvent::NextRetry(next_retry.map(|retry_time| retry_time.timestamp_millis())),
+        );
+    }
+}
+
+fn reset_retries(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    lock_state(&app_state).login_attempts = 0;
+    lock_state(&app_state).gave_up = Option::None;
+    lock_state(&app_state).probe_failures = 0;
+    set_next_retry(app, Option::None);
+}
+
+/// How long to wait after running out of data: until the most constrained
+/// quota resets when the portal said when, the longest backoff otherwise
+fn data_exceeded_delay(app: &tauri::AppHandle) -> chrono::Duration {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let state = lock_state(&app_state);
+    quota::most_constrained(&state.quotas)
+        .and_then(|quota| quota.reset_time())
+        // A minute later, in case the portal's clock is a little behind
+        .map(|reset| reset - chrono::Local::now() + chrono::Duration::minutes(1))
+        .filter(|delay| *delay > chrono::Duration::zero())
+        .unwrap_or_else(|| state.settings.retry.backoff(u32::MAX))
+}
+
+/// Handles a login attempt that did not go through. The user is notified on
+/// the first failure and when giving up, retries in between stay quiet.
+/// After giving up the loop keeps probing at the longest backoff, or until
+/// the quota resets, so that it logs in again without the user stepping in.
+fn login_failed(app: &tauri::AppHandle, failure: LoginFailure, reason: &str) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    set_connection_state(app, ConnectionState::Disconnected);
+    let attempt = lock_state(&app_state).login_attempts;
+    let delay = lock_state(&app_state)
+        .settings
+        .retry
+        .next_delay(failure, attempt);
+    if delay.is_some() {
+        let next_retry = chrono::Local::now() + delay.unwrap();
+        lock_state(&app_state).login_attempts = attempt + 1;
+        set_next_retry(app, Option::Some(next_retry));
+        if attempt == 0 {
+            ui::notify(
+                "Could not connect to Campnet!",
+                format!("{}, retrying at {}", reason, next_retry.format("%H:%M:%S")),
+            );
+            fire_event(
+                app,
+                HookEvent::LoginFailed,
+                &[
+                    ("reason", reason.to_string()),
+                    ("next_retry", next_retry.to_rfc3339()),
+                ],
+            );
+        }
+        schedule_login(app, delay.unwrap());
+    } else {
+        let delay = if failure == LoginFailure::DataExceeded {
+            data_exceeded_delay(app)
+        } else {
+            lock_state(&app_state).settings.retry.backoff(u32::MAX)
+        };
+        set_next_retry(app, Option::Some(chrono::Local::now() + delay));
+        let gave_up = lock_state(&app_state).gave_up.replace(failure);
+        if gave_up != Option::Some(failure) {
+            ui::notify(
+                "Could not connect to Campnet!",
+                if attempt == 0 {
+                    reason.to_string()
+                } else {
+                    format!("{}, gave up after {} retries", reason, attempt)
+                },
+            );
+            fire_event(
+                app,
+                HookEvent::LoginFailed,
+                &[
+                    ("reason", reason.to_string()),
+                    ("attempts", (attempt + 1).to_string()),
+                ],
+            );
+        }
+        schedule_login(app, delay);
+    }
+}
+
+/// A handshake was refused by the certificate checks from settings. Kept as
+/// its own state so that it is not mistaken for being off campus.
+fn certificate_error(app: &tauri::AppHandle, failure: TlsFailure) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    if lock_state(&app_state).connection_state != ConnectionState::CertificateError {
+        ui::notify(
+            "Campnet certificate problem!",
+            format!("{}: {}", failure.endpoint, failure.reason),
+        );
+    }
+    emit_event(app, AppEvent::TlsFailure(failure));
+    set_connection_state(app, ConnectionState::CertificateError);
+}
+
+/// The login endpoint did not answer, most likely because we are not on the
+/// campus network. Keeps probing with a capped backoff instead of giving up.
+fn portal_unreachable(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let attempt = lock_state(&app_state).probe_failures;
+    let delay = lock_state(&app_state).settings.retry.backoff(attempt);
+    lock_state(&app_state).probe_failures = attempt.saturating_add(1);
+    set_next_retry(app, Option::Some(chrono::Local::now() + delay));
+    schedule_login(app, delay);
 }
 
 fn connect_campnet(app: tauri::AppHandle, initial_run: bool) {
     if !initial_run {
         let app_state = app.state::<Arc<Mutex<AppState>>>();
-        app_state.lock().unwrap().login_guard = Option::None;
-        let tray_handle = app.tray_handle();
-        let resources_resolver = app.path_resolver();
-        let active_icon_path = resources_resolver
-            .resolve_resource("resources/icons/active.png")
-            .unwrap();
-        let used_50_icon_path = resources_resolver
-            .resolve_resource("resources/icons/used_50.png")
-            .unwrap();
-        let used_90_icon_path = resources_resolver
-            .resolve_resource("resources/icons/used_90.png")
-            .unwrap();
-        let inactive_icon_path = resources_resolver
-            .resolve_resource("resources/icons/inactive.png")
-            .unwrap();
-        let credentials = app_state.lock().unwrap().credentials.to_owned();
-        let client = reqwest::blocking::Client::new();
-        let campnet_status = client
-            .head(app_state.lock().unwrap().login_endpoint.to_owned())
-            .send();
-        if campnet_status.is_ok() {
-            let login_status = client.head("https://www.google.com").send();
-            if login_status.is_err() {
-                let res = login_campnet(
-                    client,
-                    credentials,
-                    app_state.lock().unwrap().login_endpoint.to_string(),
+        lock_state(&app_state).login_guard = Option::None;
+        let credentials = lock_state(&app_state).credentials.to_owned();
+        let client = login_client(&app);
+        let captive = captive_portal(&app);
+        if !within_login_window(&app) {
+            set_next_retry(&app, Option::None);
+            schedule_login(&app, chrono::Duration::milliseconds(2500));
+        } else if captive.detect(&client) {
+            lock_state(&app_state).probe_failures = 0;
+            let login_status = general_client(&app).head("https://www.google.com").send();
+            if login_status.is_err() && !network_trusted(&app) {
+                set_next_retry(&app, Option::None);
+                schedule_login(&app, chrono::Duration::milliseconds(2500));
+            } else if login_status.is_err() && auto_login_paused(&app) {
+                set_connection_state(&app, ConnectionState::Disconnected);
+                set_next_retry(&app, Option::None);
+                schedule_login(&app, chrono::Duration::milliseconds(2500));
+            } else if login_status.is_err()
+                && lock_state(&app_state).gave_up == Option::Some(LoginFailure::InvalidCredentials)
+            {
+                // Posting a rejected password again can get the account
+                // locked, it is only tried again once the credentials change
+                login_failed(
+                    &app,
+                    LoginFailure::InvalidCredentials,
+                    "Incorrect credentials were provided",
+                );
+            } else if login_status.is_err() {
+                let res = captive.login(
+                    &client,
+                    &credentials.username,
+                    credentials.password.expose(),
                 );
                 if res.is_ok() {
-                    let res_body: String = res.unwrap().text().unwrap();
-                    if res_body.contains("LIVE") {
-                        Notification::new("com.riskycase.autocampnet")
-                            .title("Connected to Campnet!")
-                            .body("Logged in successfully to BPGC network")
-                            .show()
-                            .unwrap();
-                        let current_notification_state =
-                            app_state.lock().unwrap().last_notification_state;
-                        if current_notification_state == NotificationState::None {
-                            tray_handle
-                                .set_icon(tauri::Icon::File(active_icon_path))
-                                .unwrap();
-                        } else if current_notification_state == NotificationState::Used50 {
-                            tray_handle
-                                .set_icon(tauri::Icon::File(used_50_icon_path))
-                                .unwrap();
-                        } else if current_notification_state == NotificationState::Used90 {
-                            tray_handle
-                                .set_icon(tauri::Icon::File(used_90_icon_path))
-                                .unwrap();
-                        }
-                        let app_handle_next = app.app_handle();
-                        let callback_timer = timer::Timer::new();
-                        let callback_gaurd = callback_timer.schedule_with_delay(
-                            chrono::Duration::milliseconds(2500),
-                            move || {
-                                connect_campnet(app_handle_next.app_handle(), false);
-                            },
+                    let outcome = res.unwrap();
+                    if outcome == LoginOutcome::LoggedIn {
+                        lock_state(&app_state).last_keepalive = Option::Some(chrono::Local::now());
+                        set_connection_state(&app, ConnectionState::Connected);
+                        reset_retries(&app);
+                        ui::notify(
+                            "Connected to Campnet!",
+                            "Logged in successfully to BPGC network",
+                        );
+                        fire_event(&app, HookEvent::Login, &[]);
+                        schedule_login(&app, chrono::Duration::milliseconds(2500));
+                    } else if outcome == LoginOutcome::LoginLimit {
+                        // Another device is holding the last slot, the user
+                        // can free it up from the sessions list
+                        emit_event(&app, AppEvent::SessionLimit);
+                        login_failed(
+                            &app,
+                            LoginFailure::Other,
+                            "Maximum login limit reached on credentials",
+                        );
+                    } else if outcome == LoginOutcome::InvalidCredentials {
+                        login_failed(
+                            &app,
+                            LoginFailure::InvalidCredentials,
+                            "Incorrect credentials were provided",
+                        );
+                    } else if outcome == LoginOutcome::DataExceeded {
+                        login_failed(
+                            &app,
+                            LoginFailure::DataExceeded,
+                            "Daily data limit exceeded on credentials",
                         );
-                        app_state.lock().unwrap().login_guard =
-                            Option::Some(callback_gaurd.to_owned());
-                        std::thread::sleep(std::time::Duration::from_secs(3));
-                    } else if res_body.contains("failed") {
-                        Notification::new("com.riskycase.autocampnet")
-                            .title("Could not connect to Campnet!")
-                            .body("Incorrect credentials were provided")
-                            .show()
-                            .unwrap();
-                        tray_handle
-                            .set_icon(tauri::Icon::File(inactive_icon_path))
-                            .unwrap();
-                    } else if res_body.contains("exceeded") {
-                        Notification::new("com.riskycase.autocampnet")
-                            .title("Could not connect to Campnet!")
-                            .body("Daily data limit exceeded on credentials")
-                            .show()
-                            .unwrap();
-                        tray_handle
-                            .set_icon(tauri::Icon::File(inactive_icon_path))
-                            .unwrap();
                     } else {
-                        Notification::new("com.riskycase.autocampnet")
-                            .title("Could not to Campnet!")
-                            .body("There was an issue with the login attempt")
-                            .show()
-                            .unwrap();
-                        tray_handle
-                            .set_icon(tauri::Icon::File(inactive_icon_path))
-                            .unwrap();
+                        login_failed(
+                            &app,
+                            LoginFailure::Other,
+                            "There was an issue with the login attempt",
+                        );
                     }
+                } else {
+                    login_failed(
+                        &app,
+                        LoginFailure::Other,
+                        "The login request did not go through",
+                    );
                 }
             } else {
-                let app_handle_next = app.app_handle();
-                let callback_timer = timer::Timer::new();
-                let callback_gaurd = callback_timer.schedule_with_delay(
-                    chrono::Duration::milliseconds(2500),
-                    move || {
-                        connect_campnet(app_handle_next.app_handle(), false);
-                    },
-                );
-                app_state.lock().unwrap().login_guard = Option::Some(callback_gaurd.to_owned());
-                tray_handle
-                    .set_icon(tauri::Icon::File(active_icon_path))
-                    .unwrap();
-                std::thread::sleep(std::time::Duration::from_secs(3));
+                set_connection_state(&app, ConnectionState::Connected);
+                reset_retries(&app);
+                keep_alive(&app);
+                schedule_login(&app, chrono::Duration::milliseconds(2500));
+            }
+        } else {
+            let was_connected =
+                lock_state(&app_state).connection_state == ConnectionState::Connected;
+            let tls_failure = app.state::<HttpClient>().login_tls_failure();
+            if tls_failure.is_some() {
+                certificate_error(&app, tls_failure.unwrap());
+            } else {
+                set_connection_state(&app, ConnectionState::Disconnected);
+            }
+            if was_connected {
+                fire_event(&app, HookEvent::NetworkLost, &[]);
             }
+            portal_unreachable(&app);
         }
     } else {
         let app_handle_next = app.app_handle();
@@ -232,327 +905,936 @@ fn connect_campnet(app: tauri::AppHandle, initial_run: bool) {
         let callback_timer = timer::Timer::new();
         let callback_gaurd =
             callback_timer.schedule_with_delay(chrono::Duration::zero(), move || {
-                connect_campnet(app_handle_next.app_handle(), false);
+                supervised_round(&app_handle_next, "Login loop", connect_campnet);
             });
-        app_state.lock().unwrap().login_guard = Option::Some(callback_gaurd.to_owned());
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        lock_state(&app_state).login_guard = Option::Some(callback_gaurd);
+        lock_state(&app_state).login_timer = Option::Some(callback_timer);
     }
 }
 
-fn get_cookie(app: tauri::AppHandle) -> Result<(), reqwest::Error> {
-    let client = reqwest::blocking::Client::new();
+fn clear_portal_session(app: &tauri::AppHandle) {
     let app_state = app.state::<Arc<Mutex<AppState>>>();
-    let credentials = app_state.lock().unwrap().credentials.clone();
-    let body: String = format!(
-        "mode=451&json=%7B%22username%22%3A%22{}%22%2C%22password%22%3A%22{}%22%2C%22languageid%22%3A%221%22%2C%22browser%22%3A%22Chrome_109%22%7D&t={}",
-        credentials.username,
-        credentials.password,
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
-    );
-    let response = client
-        .post(app_state.lock().unwrap().portal_endpoint.to_owned() + "/userportal/Controller")
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .body(body)
-        .send();
-    if response.is_ok() {
-        app_state.lock().unwrap().cookie = response
-            .unwrap()
-            .headers()
-            .get(reqwest::header::SET_COOKIE)
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .split(";")
-            .into_iter()
-            .nth(0)
-            .unwrap()
-            .to_string();
-        Ok(())
-    } else {
-        Err(response.err().unwrap())
+    lock_state(&app_state).portal_session = Option::None;
+    lock_state(&app_state).checked_session = Option::None;
+}
+
+/// The user portal backend, detected on first use and kept until the portal
+/// answers in a way it cannot read, so that a firmware upgrade is picked up.
+fn portal_backend(app: &tauri::AppHandle) -> Option<Arc<dyn PortalBackend>> {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let cached = lock_state(&app_state).portal_backend.clone();
+    if cached.is_some() {
+        return cached;
     }
+    let portal_endpoint = lock_state(&app_state).portal_endpoint.to_string();
+    let detected = portal::detect(&portal_client(app), &portal_endpoint);
+    lock_state(&app_state).portal_backend = detected.clone();
+    detected
 }
 
-fn get_csrf(app: tauri::AppHandle) -> Result<(), ()> {
-    let client = reqwest::blocking::Client::new();
+/// Logs in to the user portal, leaving the session in the app state to be
+/// reused until the portal stops accepting it.
+fn portal_login(app: &tauri::AppHandle) -> Result<(), PortalError> {
+    clear_portal_session(app);
+    let backend = portal_backend(app).ok_or(PortalError::Unavailable)?;
     let app_state = app.state::<Arc<Mutex<AppState>>>();
-    let cookie = app_state.lock().unwrap().cookie.to_string();
-    let response = client
-        .get(
-            app_state.lock().unwrap().portal_endpoint.to_string()
-                + "/userportal/webpages/myaccount/index.jsp",
-        )
-        .header(reqwest::header::COOKIE, cookie.to_string())
-        .header(
-            reqwest::header::USER_AGENT,
-            format!("AutoCampnetRuntime/{}", app.package_info().version),
-        )
-        .send();
-    if response.is_ok() {
-        let regex = Regex::new(r"k3n = '(.+)'").unwrap();
-        let body = response.unwrap().text().unwrap();
-        let matches = regex.captures(body.as_str());
-        if matches.is_some() {
-            app_state.lock().unwrap().csrf = matches
-                .unwrap()
-                .get(0)
-                .unwrap()
-                .as_str()
-                .split("'")
-                .into_iter()
-                .nth(1)
-                .unwrap()
-                .to_string();
+    let credentials = lock_state(&app_state).credentials.clone();
+    let portal_endpoint = lock_state(&app_state).portal_endpoint.to_string();
+    let session = backend.login(
+        &portal_client(app),
+        &portal_endpoint,
+        &credentials.username,
+        credentials.password.expose(),
+    );
+    match session {
+        Ok(session) => {
+            lock_state(&app_state).portal_session = Option::Some(session);
             Ok(())
-        } else {
-            Err(())
         }
+        Err(error) => {
+            // Only an answer this backend cannot read means the firmware
+            // changed, a wrong password says nothing about the backend
+            if error == PortalError::Unexpected {
+                lock_state(&app_state).portal_backend = Option::None;
+            }
+            Err(error)
+        }
+    }
+}
+
+/// Runs `action` against the user portal with the cached session, logging
+/// in first if there is no session or it turns out to have expired.
+fn with_portal<T>(
+    app: &tauri::AppHandle,
+    action: impl Fn(
+        &dyn PortalBackend,
+        &reqwest::blocking::Client,
+        &str,
+        &PortalSession,
+    ) -> Result<T, PortalError>,
+) -> Result<T, PortalError> {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let attempt = || {
+        let backend = lock_state(&app_state).portal_backend.clone();
+        let session = lock_state(&app_state).portal_session.clone();
+        let portal_endpoint = lock_state(&app_state).portal_endpoint.to_string();
+        if backend.is_none() || session.is_none() {
+            return Err(PortalError::Expired);
+        }
+        action(
+            backend.unwrap().as_ref(),
+            &portal_client(app),
+            &portal_endpoint,
+            &session.unwrap(),
+        )
+    };
+    let result = attempt();
+    if result.as_ref().err() == Option::Some(&PortalError::Expired) {
+        portal_login(app)?;
+        attempt()
     } else {
-        Err(())
+        result
     }
 }
 
+/// Reads the plan details off the user portal. These rarely change, so this
+/// only runs again once the details have been cleared.
+fn refresh_account_details(app: &tauri::AppHandle) -> Result<AccountDetails, PortalError> {
+    let details = with_portal(app, |backend, client, endpoint, session| {
+        backend.account_details(client, endpoint, session)
+    })?;
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    lock_state(&app_state).account_details = Option::Some(details.clone());
+    emit_event(app, AppEvent::AccountDetails(Option::Some(details.clone())));
+    Ok(details)
+}
+
+fn clear_account_details(app: &tauri::AppHandle) {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    lock_state(&app_state).account_details = Option::None;
+    emit_event(app, AppEvent::AccountDetails(Option::None));
+}
+
 fn get_remaining_data(app: tauri::AppHandle, initial_run: bool) {
     if !initial_run {
         let app_state = app.state::<Arc<Mutex<AppState>>>();
-        app_state.lock().unwrap().traffic_guard = Option::None;
-        let client = reqwest::blocking::Client::new();
-        let campnet_status = client
-            .head(app_state.lock().unwrap().login_endpoint.to_owned())
+        lock_state(&app_state).traffic_guard = Option::None;
+        let campnet_status = login_client(&app)
+            .head(lock_state(&app_state).login_endpoint.to_owned())
             .send();
-        if campnet_status.is_ok() {
-            let cookie_result = get_cookie(app.app_handle());
-            if cookie_result.is_ok() {
-                let csrf_result = get_csrf(app.app_handle());
-                if csrf_result.is_ok() {
-                    let cookie = app_state.lock().unwrap().cookie.to_string();
-                    let csrf = app_state.lock().unwrap().csrf.to_string();
-                    let portal_endpoint = app_state.lock().unwrap().portal_endpoint.to_string();
-                    let data_result = client
-                        .get(
-                            portal_endpoint.to_string()
-                                + "/userportal/webpages/myaccount/AccountStatus.jsp",
-                        )
-                        .query(&[
-                            ("popup", "0"),
-                            (
-                                "t",
-                                format!(
-                                    "{}",
-                                    std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_millis()
-                                )
-                                .as_str(),
-                            ),
-                        ])
-                        .header("X-CSRF-Token", csrf)
-                        .header(reqwest::header::COOKIE, cookie)
-                        .header(
-                            reqwest::header::USER_AGENT,
-                            format!("AutoCampnetRuntime/{}", app.package_info().version),
-                        )
-                        .header(
-                            reqwest::header::REFERER,
-                            portal_endpoint.to_string()
-                                + "/userportal/webpages/myaccount/login.jsp",
-                        )
-                        .send();
-                    if data_result.is_ok() {
-                        let body_text = data_result.unwrap().text().unwrap();
-                        let dom =
-                            tl::parse(body_text.as_str(), tl::ParserOptions::default()).unwrap();
-                        let parser = dom.parser();
-                        let element = dom
-                            .get_element_by_id("content3")
-                            .expect("")
-                            .get(parser)
-                            .unwrap();
-                        let table_text = element.inner_html(parser).to_string();
-                        let sub_dom =
-                            tl::parse(table_text.as_str(), tl::ParserOptions::default()).unwrap();
-                        let sub_parser = sub_dom.parser();
-                        let mut data_vector: Vec<f32> = Vec::new();
-                        let mut unit_vector: Vec<String> = Vec::new();
-                        let datas = sub_dom.query_selector("td.tabletext").unwrap();
-                        datas.for_each(|data| {
-                            data_vector.push(
-                                data.get(sub_parser)
-                                    .unwrap()
-                                    .inner_text(sub_parser)
-                                    .trim()
-                                    .replace("&nbsp;", "")
-                                    .parse::<f32>()
-                                    .unwrap(),
+        let policy = lock_state(&app_state).settings.trust.clone();
+        if campnet_status.is_ok() && trust::check_network(&policy).is_ok() {
+            let vendor = lock_state(&app_state).settings.campus.vendor;
+            let data_result = if vendor == Vendor::Sophos {
+                with_portal(&app, |backend, client, endpoint, session| {
+                    backend.quotas(client, endpoint, session)
+                })
+                .map_err(|_| ())
+            } else {
+                // Other vendors have no user portal and report usage, if at
+                // all, through the captive portal itself
+                let credentials = lock_state(&app_state).credentials.to_owned();
+                captive_portal(&app)
+                    .usage(
+                        &login_client(&app),
+                        &credentials.username,
+                        credentials.password.expose(),
+                    )
+                    .map_err(|_| ())
+            };
+            let tls_failure = app.state::<HttpClient>().portal_tls_failure();
+            if data_result.is_err() && tls_failure.is_some() {
+                certificate_error(&app, tls_failure.unwrap());
+            } else if data_result.is_ok() {
+                let quotas = data_result.unwrap();
+                lock_state(&app_state).quotas = quotas.clone();
+                lock_state(&app_state).last_update = Option::Some(chrono::Local::now());
+                emit_event(&app, AppEvent::Quotas(quotas.clone()));
+                let constrained = quota::most_constrained(&quotas);
+                if constrained.is_some() {
+                    let constrained = constrained.unwrap();
+                    // Usage of the previous cycle is only shown by older portals
+                    let (values, units) = match &constrained.legacy {
+                        Option::Some(legacy) => (legacy.values, legacy.units.clone()),
+                        Option::None => (
+                            [
+                                constrained.limit,
+                                0.0,
+                                constrained.used,
+                                constrained.used,
+                                constrained.remaining,
+                            ],
+                            std::array::from_fn(|_| constrained.unit.to_string()),
+                        ),
+                    };
+                    let traffic = TrafficStats {
+                        total: values[0],
+                        last: values[1],
+                        current: values[2],
+                        used: values[3],
+                        remaining: values[4],
+                    };
+                    lock_state(&app_state).traffic = traffic.clone();
+                    let data_usage = constrained.usage();
+                    let current_notification_state = if data_usage < 0.5 {
+                        NotificationState::None
+                    } else if data_usage < 0.9 {
+                        NotificationState::Used50
+                    } else if data_usage < 1.0 {
+                        NotificationState::Used90
+                    } else {
+                        NotificationState::Used100
+                    };
+                    let [total, last, current, used, remaining] = units;
+                    let traffic_units = TrafficUnits {
+                        total,
+                        last,
+                        current,
+                        used,
+                        remaining,
+                    };
+                    lock_state(&app_state).traffic_units = traffic_units.clone();
+                    let last_notification_state = lock_state(&app_state).last_notification_state;
+                    if last_notification_state != current_notification_state {
+                        let details = [
+                            ("quota", constrained.name().to_string()),
+                            ("used", constrained.used.to_string()),
+                            ("remaining", constrained.remaining.to_string()),
+                            ("limit", constrained.limit.to_string()),
+                            ("unit", constrained.unit.to_string()),
+                        ];
+                        if current_notification_state == NotificationState::None {
+                            fire_event(&app, HookEvent::QuotaReset, &details);
+                        } else if current_notification_state > last_notification_state {
+                            let threshold = match current_notification_state {
+                                NotificationState::Used50 => "50",
+                                NotificationState::Used90 => "90",
+                                _ => "100",
+                            };
+                            let mut details = details.to_vec();
+                            details.push(("threshold", threshold.to_string()));
+                            fire_event(&app, HookEvent::QuotaThreshold, &details);
+                        }
+                        if current_notification_state == NotificationState::Used50 {
+                            ui::notify(
+                                &format!("50% {} warning!", constrained.name()),
+                                "Consider slowing down",
                             );
-                            unit_vector.push(
-                                data.get(sub_parser)
-                                    .unwrap()
-                                    .children()
-                                    .unwrap()
-                                    .all(sub_parser)
-                                    .get(1)
-                                    .unwrap()
-                                    .outer_html(sub_parser)
-                                    .to_string()
-                                    .split(".")
-                                    .nth(1)
-                                    .unwrap()
-                                    .split("\"")
-                                    .nth(0)
-                                    .unwrap()
-                                    .to_string(),
+                        } else if current_notification_state == NotificationState::Used90 {
+                            ui::notify(
+                                &format!("90% {} warning!", constrained.name()),
+                                "Tread the interwebs slowly",
                             );
-                        });
-                        let traffic = TrafficStats {
-                            total: data_vector[6],
-                            last: data_vector[7],
-                            current: data_vector[8],
-                            used: data_vector[9],
-                            remaining: data_vector[10],
-                        };
-                        app_state.lock().unwrap().traffic = traffic.clone();
-                        let data_usage = traffic.used / traffic.total;
-                        let current_notification_state = if data_usage < 0.5 {
-                            NotificationState::None
-                        } else if data_usage < 0.9 {
-                            NotificationState::Used50
-                        } else if data_usage < 1.0 {
-                            NotificationState::Used90
-                        } else {
-                            NotificationState::Used100
-                        };
-                        let traffic_units = TrafficUnits {
-                            total: unit_vector[6].to_string(),
-                            last: unit_vector[7].to_string(),
-                            current: unit_vector[8].to_string(),
-                            used: unit_vector[9].to_string(),
-                            remaining: unit_vector[10].to_string(),
-                        };
-                        app_state.lock().unwrap().traffic_units = traffic_units.clone();
-                        if app_state.lock().unwrap().last_notification_state
-                            != current_notification_state
-                        {
-                            if current_notification_state == NotificationState::Used50 {
-                                Notification::new("com.riskycase.autocampnet")
-                                    .title("50% data warning!")
-                                    .body("Consider slowing down")
-                                    .show()
-                                    .unwrap();
-                            } else if current_notification_state == NotificationState::Used90 {
-                                Notification::new("com.riskycase.autocampnet")
-                                    .title("90% data warning!")
-                                    .body("Tread the interwebs slowly")
-                                    .show()
-                                    .unwrap();
-                            }
-                            app_state.lock().unwrap().last_notification_state =
-                                current_notification_state
                         }
-                        app.get_window("main")
-                            .unwrap()
-                            .emit("traffic", traffic.clone())
-                            .unwrap();
-                        app.get_window("main")
-                            .unwrap()
-                            .emit("traffic_units", traffic_units.clone())
-                            .unwrap();
+                        lock_state(&app_state).last_notification_state = current_notification_state;
                     }
+                    refresh_tray_icon(&app);
+                    app.state::<Mqtt>().publish_traffic(
+                        [
+                            traffic.total,
+                            traffic.last,
+                            traffic.current,
+                            traffic.used,
+                            traffic.remaining,
+                        ],
+                        &[
+                            traffic_units.total.to_string(),
+                            traffic_units.last.to_string(),
+                            traffic_units.current.to_string(),
+                            traffic_units.used.to_string(),
+                            traffic_units.remaining.to_string(),
+                        ],
+                    );
+                    emit_event(&app, AppEvent::Traffic(traffic.clone()));
+                    emit_event(&app, AppEvent::TrafficUnits(traffic_units.clone()));
+                }
+                refresh_tray_menu(&app);
+                if vendor == Vendor::Sophos && lock_state(&app_state).account_details.is_none() {
+                    refresh_account_details(&app).ok();
                 }
             }
         }
+        write_status_file(&app);
         let app_handle_next = app.app_handle();
         let callback_timer = timer::Timer::new();
-        let callback_gaurd =
-            callback_timer.schedule_with_delay(chrono::Duration::seconds(45), move || {
-                get_remaining_data(app_handle_next.app_handle(), false);
-            });
-        app_state.lock().unwrap().traffic_guard = Option::Some(callback_gaurd.to_owned());
-        std::thread::sleep(std::time::Duration::from_secs(55));
+        let callback_gaurd = callback_timer.schedule_with_delay(
+            chrono::Duration::seconds(USAGE_INTERVAL_SECS),
+            move || {
+                supervised_round(&app_handle_next, "Usage updates", get_remaining_data);
+            },
+        );
+        lock_state(&app_state).traffic_guard = Option::Some(callback_gaurd);
+        lock_state(&app_state).traffic_timer = Option::Some(callback_timer);
     } else {
         let app_handle_next = app.app_handle();
         let app_state = app.state::<Arc<Mutex<AppState>>>();
         let callback_timer = timer::Timer::new();
         let callback_gaurd =
             callback_timer.schedule_with_delay(chrono::Duration::zero(), move || {
-                get_remaining_data(app_handle_next.app_handle(), false);
+                supervised_round(&app_handle_next, "Usage updates", get_remaining_data);
             });
-        app_state.lock().unwrap().traffic_guard = Option::Some(callback_gaurd.to_owned());
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        lock_state(&app_state).traffic_guard = Option::Some(callback_gaurd);
+        lock_state(&app_state).traffic_timer = Option::Some(callback_timer);
+    }
+}
+
+/// The password typed into the window, or the saved one when it was left
+/// empty for the same username
+fn entered_password(
+    app: &tauri::AppHandle,
+    username: &str,
+    password: Option<Secret>,
+) -> Option<Secret> {
+    let saved = lock_state(&app.state::<Arc<Mutex<AppState>>>())
+        .credentials
+        .clone();
+    password.or(
+        if saved.username == username && !saved.password.is_empty() {
+            Option::Some(saved.password)
+        } else {
+            Option::None
+        },
+    )
+}
+
+/// Checks credentials by logging in to the user portal, which leaves every
+/// captive portal login of the account alone. The session is kept for when
+/// the credentials get saved, rather than left open on the portal.
+fn portal_check(
+    app: &tauri::AppHandle,
+    username: &str,
+    password: &Secret,
+) -> Result<CredentialCheck, PortalError> {
+    let backend = portal_backend(app).ok_or(PortalError::Unavailable)?;
+    let portal_endpoint = lock_state(&app.state::<Arc<Mutex<AppState>>>())
+        .portal_endpoint
+        .to_string();
+    let client = portal_client(app);
+    let session = backend.login(&client, &portal_endpoint, username, password.expose())?;
+    let quotas = backend
+        .quotas(&client, &portal_endpoint, &session)
+        .unwrap_or_default();
+    lock_state(&app.state::<Arc<Mutex<AppState>>>()).checked_session =
+        Option::Some((username.to_string(), session));
+    let quota = quota::most_constrained(&quotas).cloned();
+    Ok(CredentialCheck {
+        quota_exhausted: quota.as_ref().map(|quota| quota.remaining <= 0.0) == Option::Some(true),
+        quota,
+    })
+}
+
+/// Checks credentials with a captive portal login, only done when the user
+/// asks for it since it can disturb live sessions. No logout is sent first,
+/// so a login elsewhere is kept.
+fn captive_check(
+    app: &tauri::AppHandle,
+    username: &str,
+    password: &Secret,
+) -> Result<CredentialCheck, CredentialError> {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    if auto_login_paused(app) {
+        return Err(CredentialError::Paused);
+    }
+    if !lock_state(&app_state)
+        .settings
+        .schedule
+        .allows(chrono::Local::now().naive_local())
+    {
+        return Err(CredentialError::Schedule);
+    }
+    let res = captive_portal(app).login(&login_client(app), username, password.expose());
+    match res {
+        Ok(LoginOutcome::LoggedIn) => Ok(CredentialCheck {
+            quota: Option::None,
+            quota_exhausted: false,
+        }),
+        Ok(LoginOutcome::DataExceeded) => Ok(CredentialCheck {
+            quota: Option::None,
+            quota_exhausted: true,
+        }),
+        // The portal only counts devices once the password is accepted
+        Ok(LoginOutcome::LoginLimit) => Ok(CredentialCheck {
+            quota: Option::None,
+            quota_exhausted: false,
+        }),
+        Ok(LoginOutcome::InvalidCredentials) => Err(CredentialError::InvalidCred),
+        _ => Err(CredentialError::Unknown),
+    }
+}
+
+/// Runs a command's network requests on a thread of their own. Tauri runs
+/// synchronous commands on the main thread, which would freeze the window
+/// and tray until the portal answers or times out. `panicked` is returned
+/// if `work` panics.
+async fn off_main_thread<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+    panicked: T,
+) -> T {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .unwrap_or(panicked)
+}
+
+#[tauri::command]
+async fn credential_check(
+    username: String,
+    password: Option<Secret>,
+    captive_login: bool,
+    app: tauri::AppHandle,
+) -> Result<CredentialCheck, CredentialError> {
+    off_main_thread(
+        move || check_credentials(&app, &username, password, captive_login),
+        Err(CredentialError::Unknown),
+    )
+    .await
+}
+
+/// Checks credentials on the user portal. Only with `captive_login` does a
+/// portal that cannot tell fall back to logging in on the captive portal.
+fn check_credentials(
+    app: &tauri::AppHandle,
+    username: &str,
+    password: Option<Secret>,
+    captive_login: bool,
+) -> Result<CredentialCheck, CredentialError> {
+    let password = entered_password(app, username, password).ok_or(CredentialError::NoPassword)?;
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let client = login_client(app);
+    let campnet_status = captive_portal(app).detect(&client);
+    let policy = lock_state(&app_state).settings.trust.clone();
+    let vendor = lock_state(&app_state).settings.campus.vendor;
+    if campnet_status && trust::check_network(&policy).is_err() {
+        Err(CredentialError::Untrusted)
+    } else if campnet_status {
+        let checked = if vendor == Vendor::Sophos {
+            portal_check(app, username, &password)
+        } else {
+            Err(PortalError::Unavailable)
+        };
+        match checked {
+            Ok(check) => Ok(check),
+            Err(PortalError::Rejected) => Err(CredentialError::InvalidCred),
+            Err(_) if captive_login => captive_check(app, username, &password),
+            Err(_) => Err(CredentialError::Unverifiable),
+        }
+    } else if app.state::<HttpClient>().login_tls_failure().is_some() {
+        Err(CredentialError::Certificate)
+    } else {
+        Err(CredentialError::NoSophos)
     }
 }
 
+/// Saves credentials from the window. Leaving the password out keeps the
+/// saved one, so the window never needs to hold it.
 #[tauri::command]
-fn credential_check(
+fn save_credentials(
     username: String,
-    password: String,
+    password: Option<Secret>,
+    app: tauri::AppHandle,
+) -> Result<(), CredentialError> {
+    let password =
+        entered_password(&app, &username, password).ok_or(CredentialError::NoPassword)?;
+    let creds = Credentials { username, password };
+    // Saved first, so that nothing changes when the file cannot be written
+    config_file(&app, "credentials.json")
+        .and_then(|save_file| save_creds(creds.clone(), &save_file))
+        .map_err(|err| {
+            error::report("Saving credentials", &err);
+            CredentialError::NotSaved
+        })?;
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    lock_state(&app_state).credentials = creds.clone();
+    let checked_session = lock_state(&app_state).checked_session.take();
+    clear_portal_session(&app);
+    lock_state(&app_state).portal_session = checked_session
+        .filter(|(username, _)| *username == creds.username)
+        .map(|(_, session)| session);
+    clear_account_details(&app);
+    reset_retries(&app);
+    emit_event(&app, AppEvent::Credentials(creds.view()));
+    let app_handle_thread = app.app_handle();
+    std::thread::spawn(move || {
+        supervised_round(&app_handle_thread, "Login loop", connect_campnet);
+        supervised_round(&app_handle_thread, "Usage updates", get_remaining_data);
+    });
+    ui::notify(
+        "Credentials saved to disk",
+        "App will try to login to campnet whenever available",
+    );
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_account_details(app: tauri::AppHandle) -> Result<AccountDetails, String> {
+    let error = "Could not read account details from the user portal".to_string();
+    let panicked = Err(error.clone());
+    off_main_thread(
+        move || {
+            let app_state = app.state::<Arc<Mutex<AppState>>>();
+            let cached = lock_state(&app_state).account_details.clone();
+            if cached.is_some() {
+                Ok(cached.unwrap())
+            } else {
+                refresh_account_details(&app).map_err(|_| error)
+            }
+        },
+        panicked,
+    )
+    .await
+}
+
+/// Everything the window shows, read when it loads instead of waiting for
+/// the next round of events
+#[tauri::command]
+fn get_status(app: tauri::AppHandle) -> AppStatus {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let state = lock_state(&app_state);
+    AppStatus {
+        credentials: state.credentials.view(),
+        traffic: state.traffic.clone(),
+        traffic_units: state.traffic_units.clone(),
+        quotas: state.quotas.clone(),
+        account_details: state.account_details.clone(),
+        autolaunch: app
+            .state::<AutoLaunchManager>()
+            .is_enabled()
+            .unwrap_or(false),
+        next_retry: state
+            .next_retry
+            .map(|retry_time| retry_time.timestamp_millis()),
+        connection_state: state.connection_state,
+        schedule_transition: state.schedule_transition,
+        // Read without clearing it, the login loop and credential checks
+        // depend on it too
+        tls_failure: if state.connection_state == ConnectionState::CertificateError {
+            let http_client = app.state::<HttpClient>();
+            http_client
+                .login_tls_failure()
+                .or_else(|| http_client.portal_tls_failure())
+        } else {
+            Option::None
+        },
+    }
+}
+
+#[tauri::command]
+fn set_autolaunch(enabled: bool, app: tauri::AppHandle) -> AppResult<()> {
+    let manager = app.state::<AutoLaunchManager>();
+    let result = if enabled {
+        manager.enable()
+    } else {
+        manager.disable()
+    };
+    auto_launch_check(app.app_handle());
+    result
+}
+
+/// Hides the window to the tray
+#[tauri::command]
+fn minimise(app: tauri::AppHandle) -> AppResult<()> {
+    Ok(ui::main_window(&app)?.hide()?)
+}
+
+/// Settings for the window to edit, without the secrets in them
+#[tauri::command]
+fn get_settings(app: tauri::AppHandle) -> Settings {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    let settings = lock_state(&app_state).settings.redacted();
+    settings
+}
+
+/// Checks and applies settings from the window, keeping the old ones when
+/// the campus, schedule or HTTP settings do not work. Secrets the window
+/// leaves out stay as they were.
+#[tauri::command]
+fn save_settings(mut settings: Settings, app: tauri::AppHandle) -> AppResult<()> {
+    let app_state = app.state::<Arc<Mutex<AppState>>>();
+    settings.keep_secrets(&lock_state(&app_state).settings);
+    let config_dir = app
+        .path_resolver()
+        .app_config_dir()
+        .ok_or(AppError::NoPath("config"))?;
+    // Load the campus first so that a broken recipe leaves the HTTP client
+    // untouched
+    let campus_portal = captive::for_profile(&settings.campus, &config_dir);
+    campus_portal
+        .clone()
+        .and_then(|_| settings.schedule.validate())
+        .and_then(|_| {
+            app.state::<HttpClient>().rebuild(
+                &settings.http,
+                &settings.tls,
+                &settings.trust,
+                &app.package_info().version.to_string(),
+            )
+        })?;
+    if lock_state(&app_state).settings.campus != settings.campus {
+        apply_campus(&app, &settings.campus, campus_portal.unwrap());
+        clear_account_details(&app);
+    }
+    webhooks::assign_ids(&mut settings.webhooks);
+    let mqtt_changed = lock_state(&app_state).settings.mqtt != settings.mqtt;
+    lock_state(&app_state).settings = settings.clone();
+    settings::save_settings(&settings, &config_dir.join("settings.json"))?;
+    refresh_tray_icon(&app);
+    if mqtt_changed {
+        start_mqtt(&app);
+    }
+    Ok(())
+}
+
+/// Other logins of this account, as listed on the user portal
+#[tauri::command]
+async fn list_sessions(app: tauri::AppHandle) -> Result<Vec<sessions::ActiveSession>, String> {
+    let error = "Could not read active sessions from the user portal".to_string();
+    let panicked = Err(error.clone());
+    off_main_thread(
+        move || {
+            with_portal(&app, |backend, client, endpoint, session| {
+                backend.sessions(client, endpoint, session)
+            })
+            .map_err(|_| error)
+        },
+        panicked,
+    )
+    .await
+}
+
+#[tauri::command]
+async fn terminate_session(id: String, app: tauri::AppHandle) -> Result<(), String> {
+    let error = "Could not log out the session".to_string();
+    let panicked = Err(error.clone());
+    off_main_thread(
+        move || {
+            with_portal(&app, |backend, client, endpoint, session| {
+                backend.terminate_session(client, endpoint, session, &id)
+            })
+            .map_err(|_| error)
+        },
+        panicked,
+    )
+    .await
+}
+
+/// Changes the password through the user portal, then logs in to the portal
+/// with the new one before replacing the saved credentials. Like the rest
+/// of the app, passwords arrive URL encoded.
+#[tauri::command]
+async fn change_password(
+    current_password: Secret,
+    new_password: Secret,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    off_main_thread(
+        move || change_portal_password(app, current_password, new_password),
+        Err("NOSESSION".to_string()),
+    )
+    .await
+}
+
+fn change_portal_password(
     app: tauri::AppHandle,
+    current_password: Secret,
+    new_password: Secret,
 ) -> Result<(), String> {
     let app_state = app.state::<Arc<Mutex<AppState>>>();
-    let client = reqwest::blocking::Client::new();
-    let campnet_status = client
-        .head(app_state.lock().unwrap().login_endpoint.to_owned())
-        .send();
-    if campnet_status.is_ok() {
-        let body: String = format!(
-            "mode=193&username={}&a={}&producttype=1",
-            username,
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()
-        );
-        let initial_res = client
-            .post(app_state.lock().unwrap().login_endpoint.to_owned() + "/logout.xml")
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .header("Content-Length", body.chars().count())
-            .body(body)
-            .send();
-        if initial_res.is_ok() {
-            let res = login_campnet(
-                client,
-                Credentials { username, password },
-                app_state.lock().unwrap().login_endpoint.to_string(),
+    let old_credentials = lock_state(&app_state).credentials.clone();
+    if old_credentials.username.is_empty() || old_credentials.password != current_password {
+        return Err("INVALIDCRED".to_string());
+    }
+    let changed = with_portal(&app, |backend, client, endpoint, session| {
+        backend.change_password(
+            client,
+            endpoint,
+            session,
+            &old_credentials.username,
+            current_password.expose(),
+            new_password.expose(),
+        )
+    });
+    match changed {
+        Err(PortalError::Rejected) => return Err("REJECTED".to_string()),
+        Err(_) => return Err("NOSESSION".to_string()),
+        Ok(()) => {}
+    }
+    let backend = portal_backend(&app);
+    let portal_endpoint = lock_state(&app_state).portal_endpoint.to_string();
+    let verified = backend.ok_or(PortalError::Unavailable).and_then(|backend| {
+        backend.login(
+            &portal_client(&app),
+            &portal_endpoint,
+            &old_credentials.username,
+            new_password.expose(),
+        )
+    });
+    // The portal accepted the change, so the old password is most likely
+    // gone even when logging in with the new one failed
+    let new_credentials = Credentials {
+        username: old_credentials.username.to_string(),
+        password: new_password,
+    };
+    lock_state(&app_state).credentials = new_credentials.clone();
+    lock_state(&app_state).portal_session = verified.as_ref().ok().cloned();
+    reset_retries(&app);
+    emit_event(&app, AppEvent::Credentials(new_credentials.view()));
+    config_file(&app, "credentials.json")
+        .and_then(|save_file| save_creds(new_credentials.clone(), &save_file))
+        .map_err(|err| {
+            error::report("Saving credentials", &err);
+            "NOTSAVED".to_string()
+        })?;
+    if verified.is_err() {
+        return Err("UNVERIFIED".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn list_interfaces() -> Result<Vec<http::LocalInterface>, String> {
+    http::local_interfaces()
+}
+
+fn auto_launch_check(app: tauri::AppHandle) {
+    emit_event(
+        &app,
+        AppEvent::Autolaunch(
+            app.state::<AutoLaunchManager>()
+                .is_enabled()
+                .or_report("Start at boot")
+                .unwrap_or(false),
+        ),
+    );
+}
+
+/// Handles a tray menu item, also used for commands from MQTT
+fn tray_action(app: &tauri::AppHandle, id: &str) {
+    match id {
+        "quit" => {
+            std::process::exit(0);
+        }
+        "show" => {
+            let app_state = app.state::<Arc<Mutex<AppState>>>();
+            auto_launch_check(app.app_handle());
+            emit_event(
+                app,
+                AppEvent::Credentials(lock_state(&app_state).credentials.view()),
+            );
+            emit_event(
+                app,
+                AppEvent::Traffic(lock_state(&app_state).traffic.clone()),
             );
+            emit_event(
+                app,
+                AppEvent::TrafficUnits(lock_state(&app_state).traffic_units.clone()),
+            );
+            emit_event(app, AppEvent::Quotas(lock_state(&app_state).quotas.clone()));
+            ui::show_main_window(app, true);
+        }
+        "logout" => {
+            let app_handle_logout = app.app_handle();
+            let client = login_client(app);
+            let app_state = app_handle_logout.state::<Arc<Mutex<AppState>>>();
+            lock_state(&app_state).login_guard = Option::None;
+            let username = lock_state(&app_state).credentials.username.to_string();
+            let res = captive_portal(app).logout(&client, &username);
             if res.is_ok() {
-                let res_body: String = res.unwrap().text().unwrap();
-                if res_body.contains("LIVE") || res_body.contains("exceeded") {
-                    Ok(())
-                } else if res_body.contains("failed") {
-                    Err("INVALIDCRED".to_string())
-                } else {
-                    Err("UNKNOWN".to_string())
+                // Stay logged out until asked otherwise, reconnecting
+                // included
+                if lock_state(&app_state).pause.is_none() {
+                    pause_auto_login(app, Option::Some(Pause::UntilRestart));
                 }
+                set_connection_state(app, ConnectionState::Disconnected);
+                ui::notify("Logged out of campnet!", "");
+                fire_event(app, HookEvent::Logout, &[("reason", "tray".to_string())]);
             } else {
-                Err("UNKNOWN".to_string())
+                ui::notify("Unable to logout of campnet!", "");
             }
-        } else {
-            Err("UNKNOWN".to_string())
         }
-    } else {
-        Err("NOSOPHOS".to_string())
+        "copy_status" => {
+            app.clipboard_manager()
+                .write_text(status_lines(app).join("\n"))
+                .or_report("Clipboard");
+        }
+        "pause_30" => {
+            pause_auto_login(
+                app,
+                Option::Some(Pause::Until(
+                    chrono::Local::now() + chrono::Duration::minutes(30),
+                )),
+            );
+        }
+        "pause_60" => {
+            pause_auto_login(
+                app,
+                Option::Some(Pause::Until(
+                    chrono::Local::now() + chrono::Duration::hours(1),
+                )),
+            );
+        }
+        "pause_restart" => {
+            pause_auto_login(app, Option::Some(Pause::UntilRestart));
+        }
+        "pause_indefinitely" => {
+            pause_auto_login(app, Option::Some(Pause::Indefinitely));
+        }
+        "resume" => {
+            pause_auto_login(app, Option::None);
+            restart_login_loop(app);
+        }
+        "reconnect" => {
+            let app_state = app.state::<Arc<Mutex<AppState>>>();
+            let creds = lock_state(&app_state).credentials.to_owned();
+            lock_state(&app_state).login_guard = Option::None;
+            lock_state(&app_state).traffic_guard = Option::None;
+            if creds.username.is_empty() || creds.password.is_empty() {
+                ui::show_main_window(app, false);
+            } else {
+                reset_retries(app);
+                let app_handle_thread = app.app_handle();
+                std::thread::spawn(move || {
+                    supervised_round(&app_handle_thread, "Login loop", connect_campnet);
+                    supervised_round(&app_handle_thread, "Usage updates", get_remaining_data);
+                });
+            }
+        }
+        "delete" => {
+            config_file(app, "credentials.json")
+                .and_then(|file_path| {
+                    if file_path.exists() {
+                        std::fs::remove_file(&file_path)?;
+                    }
+                    Ok(())
+                })
+                .or_report("Deleting credentials");
+            let app_state = app.state::<Arc<Mutex<AppState>>>();
+            lock_state(&app_state).login_guard = Option::None;
+            lock_state(&app_state).traffic_guard = Option::None;
+            lock_state(&app_state).credentials = Credentials {
+                username: "".to_owned(),
+                password: Secret::default(),
+            };
+            clear_portal_session(app);
+            clear_account_details(app);
+            lock_state(&app_state).quotas = Vec::new();
+            lock_state(&app_state).last_update = Option::None;
+            refresh_tray_icon(app);
+            refresh_tray_menu(app);
+            lock_state(&app_state).traffic = TrafficStats {
+                total: 0.0,
+                last: 0.0,
+                current: 0.0,
+                used: 0.0,
+                remaining: 0.0,
+            };
+            lock_state(&app_state).traffic_units = TrafficUnits {
+                total: "".to_string(),
+                last: "".to_string(),
+                current: "".to_string(),
+                used: "".to_string(),
+                remaining: "".to_string(),
+            };
+            auto_launch_check(app.app_handle());
+            emit_event(
+                app,
+                AppEvent::Credentials(lock_state(&app_state).credentials.view()),
+            );
+            emit_event(
+                app,
+                AppEvent::Traffic(lock_state(&app_state).traffic.clone()),
+            );
+            emit_event(
+                app,
+                AppEvent::TrafficUnits(lock_state(&app_state).traffic_units.clone()),
+            );
+            emit_event(app, AppEvent::Quotas(lock_state(&app_state).quotas.clone()));
+            ui::show_main_window(app, false);
+        }
+        _ => {}
     }
 }
 
-fn auto_launch_check(app: tauri::AppHandle) {
-    let window: tauri::Window = app.get_window("main").unwrap();
-    window
-        .emit(
-            "autolaunch",
-            app.state::<AutoLaunchManager>().is_enabled().unwrap(),
-        )
-        .unwrap();
+/// Builds the HTTP clients from saved settings. A CA bundle that went
+/// missing, a bound interface that is down or a broken proxy should not keep
+/// the app from starting, so the settings behind the failure are dropped
+/// and the user is told which. Failing everything else the defaults are
+/// used without a proxy, which leave nothing on the system to trip over.
+fn startup_http_client(settings: &mut Settings, version: &str) -> HttpClient {
+    let saved = HttpClient::new(&settings.http, &settings.tls, &settings.trust, version);
+    if saved.is_ok() {
+        return saved.unwrap();
+    }
+    let error = saved.err().unwrap();
+    let network = HttpSettings {
+        bind: Option::None,
+        proxy: ProxySetting::System,
+        ..settings.http.clone()
+    };
+    for (reset_tls, reset_network) in [(true, false), (false, true), (true, true)] {
+        let tls = if reset_tls {
+            TlsSettings::default()
+        } else {
+            settings.tls.clone()
+        };
+        let http = if reset_network {
+            network.clone()
+        } else {
+            settings.http.clone()
+        };
+        let fallback = HttpClient::new(&http, &tls, &settings.trust, version);
+        if fallback.is_ok() {
+            if reset_tls {
+                ui::notify("Ignoring certificate settings", error.to_string());
+                settings.tls = tls;
+            }
+            if reset_network {
+                ui::notify("Ignoring proxy and interface settings", error.to_string());
+                settings.http = http;
+            }
+            return fallback.unwrap();
+        }
+    }
+    ui::notify("Ignoring all network settings", error.to_string());
+    settings.http = HttpSettings {
+        proxy: ProxySetting::Direct,
+        ..HttpSettings::default()
+    };
+    settings.tls = TlsSettings::default();
+    HttpClient::new(&settings.http, &settings.tls, &settings.trust, version)
+        .expect("default HTTP settings always build a client")
 }
 
 fn main() {
+    // Status bars run the app with this flag to read the running one's status
+    if std::env::args().any(|arg| arg == "--waybar") {
+        println!("{}", status_file::waybar());
+        return;
+    }
+    let pause_menu = tauri::SystemTrayMenu::new()
+        .add_item(tauri::CustomMenuItem::new("pause_30", "For 30 minutes"))
+        .add_item(tauri::CustomMenuItem::new("pause_60", "For 1 hour"))
+        .add_item(tauri::CustomMenuItem::new("pause_restart", "Until restart"))
+        .add_item(tauri::CustomMenuItem::new(
+            "pause_indefinitely",
+            "Until resumed",
+        ));
     let tray_menu = tauri::SystemTrayMenu::new()
+        .add_item(tauri::CustomMenuItem::new("status", "Disconnected").disabled())
+        .add_item(tauri::CustomMenuItem::new("remaining", "Remaining: unknown").disabled())
+        .add_item(tauri::CustomMenuItem::new("updated", "Updated: never").disabled())
+        .add_item(tauri::CustomMenuItem::new("schedule", "Login hours: always").disabled())
+        .add_native_item(tauri::SystemTrayMenuItem::Separator)
         .add_item(tauri::CustomMenuItem::new("show", "Show window"))
+        .add_item(tauri::CustomMenuItem::new(
+            "copy_status",
+            "Copy status to clipboard",
+        ))
         .add_native_item(tauri::SystemTrayMenuItem::Separator)
         .add_item(tauri::CustomMenuItem::new("reconnect", "Force reconnect"))
+        .add_submenu(tauri::SystemTraySubmenu::new(
+            "Pause auto-login",
+            pause_menu,
+        ))
+        .add_item(tauri::CustomMenuItem::new("resume", "Resume auto-login").disabled())
         .add_item(tauri::CustomMenuItem::new("logout", "Logout"))
         .add_item(tauri::CustomMenuItem::new("delete", "Delete credentials"))
         .add_native_item(tauri::SystemTrayMenuItem::Separator)
@@ -560,16 +1842,46 @@ fn main() {
     let system_tray = tauri::SystemTray::new().with_menu(tray_menu);
     tauri::Builder::default()
         .setup(|app: &mut tauri::App| {
+            // Without a config directory nothing is kept between runs, but
+            // the app still logs in
+            let config_dir = app
+                .path_resolver()
+                .app_config_dir()
+                .ok_or(AppError::NoPath("config"))
+                .or_report("Starting up")
+                .unwrap_or_else(|| {
+                    ui::notify(
+                        "Settings will not be saved",
+                        "No config directory was found",
+                    );
+                    std::env::temp_dir().join(&app.config().tauri.bundle.identifier)
+                });
+            // Before anything else, so that a panic while starting up leaves
+            // a crash report too
+            error::init(
+                app.path_resolver()
+                    .app_log_dir()
+                    .unwrap_or_else(|| config_dir.join("logs")),
+                config_dir.clone(),
+                app.package_info().version.to_string(),
+            );
+            app.manage(TrayIcon::new(
+                app.path_resolver()
+                    .resolve_resource("resources/icons/base.png"),
+            ));
+            app.manage(Mqtt::default());
             app.manage(Arc::new(Mutex::new(AppState {
-                login_endpoint: "https://campnet.bits-goa.ac.in:8090".to_string(),
+                login_endpoint: CampusProfile::default().login_endpoint,
                 credentials: Credentials {
                     username: "".to_string(),
-                    password: "".to_string(),
+                    password: Secret::default(),
                 },
                 login_guard: Option::None,
-                portal_endpoint: "https://campnet.bits-goa.ac.in:4443".to_string(),
-                cookie: "".to_string(),
-                csrf: "".to_string(),
+                login_timer: Option::None,
+                portal_endpoint: CampusProfile::default().portal_endpoint,
+                portal_backend: Option::None,
+                portal_session: Option::None,
+                checked_session: Option::None,
                 traffic: TrafficStats {
                     total: 0.0,
                     last: 0.0,
@@ -585,52 +1897,96 @@ fn main() {
                     remaining: "".to_string(),
                 },
                 traffic_guard: Option::None,
+                traffic_timer: Option::None,
                 last_notification_state: NotificationState::None,
+                connection_state: ConnectionState::Disconnected,
+                login_attempts: 0,
+                gave_up: Option::None,
+                probe_failures: 0,
+                next_retry: Option::None,
+                settings: Settings::default(),
+                account_details: Option::None,
+                quotas: Vec::new(),
+                captive: captive::built_in(),
+                last_keepalive: Option::None,
+                pause: Option::None,
+                last_update: Option::None,
+                schedule_transition: Option::None,
             })));
-            let file_path = app
-                .path_resolver()
-                .app_config_dir()
-                .unwrap()
-                .join("credentials.json");
-            let creds = load_creds(&file_path);
-            let app_handle_save = app.app_handle();
-            app.listen_global("save", move |event: tauri::Event| {
-                let creds: Credentials = serde_json::from_str(event.payload().unwrap()).unwrap();
-                let app_state = app_handle_save.state::<Arc<Mutex<AppState>>>();
-                app_state.lock().unwrap().credentials = creds.clone();
-                save_creds(creds, &file_path);
-                let app_handle_thread = app_handle_save.app_handle();
-                std::thread::spawn(move || {
-                    connect_campnet(app_handle_thread.app_handle(), false);
-                    get_remaining_data(app_handle_thread.app_handle(), false);
-                });
-                Notification::new("com.riskycase.autocampnet")
-                    .title("Credentials saved to disk")
-                    .body("App will try to login to campnet whenever available")
-                    .show()
-                    .unwrap();
-            });
-            let app_handle_minimise = app.app_handle();
-            app.listen_global("minimise", move |_event: tauri::Event| {
-                app_handle_minimise
-                    .get_window("main")
-                    .unwrap()
-                    .hide()
-                    .unwrap();
+            let creds = load_creds(&config_dir.join("credentials.json"));
+            let mut loaded_settings = settings::load_settings(&config_dir.join("settings.json"));
+            if webhooks::assign_ids(&mut loaded_settings.webhooks) {
+                // Saved before any of the fallbacks below are applied
+                settings::save_settings(&loaded_settings, &config_dir.join("settings.json"))
+                    .or_report("Saving webhook ids");
+            }
+            let version = app.package_info().version.to_string();
+            app.manage(startup_http_client(&mut loaded_settings, &version));
+            let campus_portal = captive::for_profile(&loaded_settings.campus, &config_dir);
+            if campus_portal.is_ok() {
+                apply_campus(
+                    &app.app_handle(),
+                    &loaded_settings.campus,
+                    campus_portal.unwrap(),
+                );
+            } else {
+                // Same as above, a broken recipe falls back to the built in campus
+                ui::notify("Ignoring campus settings", campus_portal.err().unwrap());
+                loaded_settings.campus = CampusProfile::default();
+            }
+            if let Err(error) = loaded_settings.schedule.validate() {
+                // A window that cannot be read never opens, which would keep
+                // the app logged out for good
+                ui::notify("Ignoring login hours", error);
+                loaded_settings.schedule = schedule::Schedule::default();
+            }
+            lock_state(&app.state::<Arc<Mutex<AppState>>>()).settings = loaded_settings;
+            publish_connection_state(&app.app_handle());
+            start_mqtt(&app.app_handle());
+            app.manage(Webhooks::new(config_dir.join("webhooks.json")));
+            let app_handle_webhooks = app.app_handle();
+            std::thread::spawn(move || {
+                error::supervise(
+                    "Webhook delivery",
+                    || loop {
+                        let targets =
+                            lock_state(&app_handle_webhooks.state::<Arc<Mutex<AppState>>>())
+                                .settings
+                                .webhooks
+                                .clone();
+                        let webhooks = app_handle_webhooks.state::<Webhooks>();
+                        webhooks.deliver(&general_client(&app_handle_webhooks), &targets);
+                        webhooks.wait();
+                    },
+                    || {
+                        app_handle_webhooks
+                            .state::<Arc<Mutex<AppState>>>()
+                            .clear_poison();
+                        app_handle_webhooks.state::<Webhooks>().clear_poison();
+                    },
+                )
             });
             let mut auto_launch_builder = AutoLaunchBuilder::new();
             auto_launch_builder.set_app_name(&app.package_info().name);
-            let currnet_exe = current_exe();
+            // Without a path start at boot is left unavailable, rather than
+            // keeping the app from starting
+            let currnet_exe = current_exe().or_report("Start at boot");
             #[cfg(windows)]
-            auto_launch_builder.set_app_path(&currnet_exe.unwrap().display().to_string());
+            if let Option::Some(currnet_exe) = &currnet_exe {
+                auto_launch_builder.set_app_path(&currnet_exe.display().to_string());
+            }
             #[cfg(target_os = "macos")]
-            {
+            if let Option::Some(currnet_exe) = &currnet_exe {
                 // on macOS, current_exe gives path to /Applications/Example.app/MacOS/Example
                 // but this results in seeing a Unix Executable in macOS login items
                 // It must be: /Applications/Example.app
                 // If it didn't find exactly a single occurance of .app, it will default to
                 // exe path to not break it.
-                let exe_path = currnet_exe.unwrap().canonicalize()?.display().to_string();
+                let exe_path = currnet_exe
+                    .canonicalize()
+                    .unwrap_or_else(|_| currnet_exe.clone())
+                    .display()
+                    .to_string();
                 let parts: Vec<&str> = exe_path.split(".app/").collect();
                 let app_path = if parts.len() == 2 {
                     format!("{}.app", parts.get(0).unwrap().to_string())
@@ -646,169 +2002,34 @@ fn main() {
                 .and_then(|p| p.to_str().map(|s| s.to_string()))
             {
                 auto_launch_builder.set_app_path(&appimage);
-            } else {
-                auto_launch_builder.set_app_path(&currnet_exe.unwrap().display().to_string());
+            } else if let Option::Some(currnet_exe) = &currnet_exe {
+                auto_launch_builder.set_app_path(&currnet_exe.display().to_string());
             }
 
             app.manage(AutoLaunchManager(
-                auto_launch_builder.build().map_err(|e| e.to_string())?,
+                auto_launch_builder.build().or_report("Start at boot"),
             ));
 
-            let app_handle_launch = app.app_handle();
-            app.listen_global("autolaunch", move |event: tauri::Event| {
-                if event.payload().unwrap().parse::<bool>().unwrap() {
-                    app_handle_launch
-                        .state::<AutoLaunchManager>()
-                        .enable()
-                        .unwrap();
-                } else {
-                    app_handle_launch
-                        .state::<AutoLaunchManager>()
-                        .disable()
-                        .unwrap();
-                }
-                auto_launch_check(app_handle_launch.app_handle());
-            });
-
-            std::fs::create_dir_all(app.path_resolver().app_config_dir().unwrap()).unwrap();
+            std::fs::create_dir_all(&config_dir).or_report("Creating the config directory");
             let app_state: State<Arc<Mutex<AppState>>> = app.state::<Arc<Mutex<AppState>>>();
+            // Restore the pause before the first login attempt
+            let pause = load_pause(&config_dir.join("pause.json"));
+            if pause.is_some() {
+                pause_auto_login(&app.app_handle(), pause);
+            }
             if creds.is_ok() {
-                app_state.lock().unwrap().login_endpoint =
-                    String::from("https://campnet.bits-goa.ac.in:8090");
-                app_state.lock().unwrap().portal_endpoint =
-                    String::from("https://campnet.bits-goa.ac.in:4443");
-                app_state.lock().unwrap().credentials = creds.unwrap();
+                lock_state(&app_state).credentials = creds.unwrap();
                 connect_campnet(app.app_handle(), true);
                 get_remaining_data(app.app_handle(), true);
             } else {
-                app.get_window("main").unwrap().show().unwrap();
+                ui::show_main_window(&app.app_handle(), false);
                 auto_launch_check(app.app_handle());
             }
             Ok(())
         })
         .system_tray(system_tray)
         .on_system_tray_event(|app: &tauri::AppHandle, event| match event {
-            tauri::SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
-                "quit" => {
-                    std::process::exit(0);
-                }
-                "show" => {
-                    let app_state = app.state::<Arc<Mutex<AppState>>>();
-                    auto_launch_check(app.app_handle());
-                    let window: tauri::Window = app.get_window("main").unwrap();
-                    window
-                        .emit("credentials", app_state.lock().unwrap().credentials.clone())
-                        .unwrap();
-                    window
-                        .emit("traffic", app_state.lock().unwrap().traffic.clone())
-                        .unwrap();
-                    window
-                        .emit(
-                            "traffic_units",
-                            app_state.lock().unwrap().traffic_units.clone(),
-                        )
-                        .unwrap();
-                    window.show().unwrap();
-                    window.unminimize().unwrap();
-                    window.set_focus().unwrap();
-                }
-                "logout" => {
-                    let app_handle_logout = app.app_handle();
-                    let client = reqwest::blocking::Client::new();
-                    let app_state = app_handle_logout.state::<Arc<Mutex<AppState>>>();
-                    app_state.lock().unwrap().login_guard = Option::None;
-                    let body: String = format!(
-                        "mode=193&username={}&a={}&producttype=1",
-                        app_state.lock().unwrap().credentials.username,
-                        std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis()
-                    );
-                    let res = client
-                        .post(app_state.lock().unwrap().login_endpoint.to_owned() + "/logout.xml")
-                        .header("Content-Type", "application/x-www-form-urlencoded")
-                        .header("Content-Length", body.chars().count())
-                        .body(body)
-                        .send();
-                    if res.is_ok() {
-                        app.tray_handle()
-                            .set_icon(tauri::Icon::File(
-                                app.path_resolver()
-                                    .resolve_resource("resources/icons/inactive.png")
-                                    .unwrap(),
-                            ))
-                            .unwrap();
-                        Notification::new("com.riskycase.autocampnet")
-                            .title("Logged out of campnet!")
-                            .show()
-                            .unwrap();
-                    } else {
-                        Notification::new("com.riskycase.autocampnet")
-                            .title("Unable to logout of campnet!")
-                            .show()
-                            .unwrap();
-                    }
-                }
-                "reconnect" => {
-                    let app_state = app.state::<Arc<Mutex<AppState>>>();
-                    let creds = app_state.lock().unwrap().credentials.to_owned();
-                    app_state.lock().unwrap().login_guard = Option::None;
-                    app_state.lock().unwrap().traffic_guard = Option::None;
-                    if (creds.username.len() == 0) | (creds.password.len() == 0) {
-                        let window: tauri::Window = app.get_window("main").unwrap();
-                        window.show().unwrap();
-                    } else {
-                        connect_campnet(app.app_handle(), false);
-                        get_remaining_data(app.app_handle(), false);
-                    }
-                }
-                "delete" => {
-                    let file_path = app
-                        .path_resolver()
-                        .app_config_dir()
-                        .unwrap()
-                        .join("credentials.json");
-                    std::fs::remove_file(&file_path).unwrap();
-                    let app_state = app.state::<Arc<Mutex<AppState>>>();
-                    app_state.lock().unwrap().login_guard = Option::None;
-                    app_state.lock().unwrap().traffic_guard = Option::None;
-                    app_state.lock().unwrap().credentials = Credentials {
-                        username: "".to_owned(),
-                        password: "".to_owned(),
-                    };
-                    app_state.lock().unwrap().traffic = TrafficStats {
-                        total: 0.0,
-                        last: 0.0,
-                        current: 0.0,
-                        used: 0.0,
-                        remaining: 0.0,
-                    };
-                    app_state.lock().unwrap().traffic_units = TrafficUnits {
-                        total: "".to_string(),
-                        last: "".to_string(),
-                        current: "".to_string(),
-                        used: "".to_string(),
-                        remaining: "".to_string(),
-                    };
-                    auto_launch_check(app.app_handle());
-                    let window: tauri::Window = app.get_window("main").unwrap();
-                    window
-                        .emit("credentials", app_state.lock().unwrap().credentials.clone())
-                        .unwrap();
-                    window
-                        .emit("traffic", app_state.lock().unwrap().traffic.clone())
-                        .unwrap();
-                    window
-                        .emit(
-                            "traffic_units",
-                            app_state.lock().unwrap().traffic_units.clone(),
-                        )
-                        .unwrap();
-                    window.show().unwrap();
-                }
-                _ => {}
-            },
+            tauri::SystemTrayEvent::MenuItemClick { id, .. } => tray_action(app, id.as_str()),
             tauri::SystemTrayEvent::LeftClick {
                 tray_id: _,
                 position: _,
@@ -817,26 +2038,37 @@ fn main() {
             } => {
                 let app_state = app.state::<Arc<Mutex<AppState>>>();
                 auto_launch_check(app.app_handle());
-                let window: tauri::Window = app.get_window("main").unwrap();
-                window
-                    .emit("credentials", app_state.lock().unwrap().credentials.clone())
-                    .unwrap();
-                window
-                    .emit("traffic", app_state.lock().unwrap().traffic.clone())
-                    .unwrap();
-                window
-                    .emit(
-                        "traffic_units",
-                        app_state.lock().unwrap().traffic_units.clone(),
-                    )
-                    .unwrap();
-                window.show().unwrap();
-                window.unminimize().unwrap();
-                window.set_focus().unwrap();
+                emit_event(
+                    app,
+                    AppEvent::Credentials(lock_state(&app_state).credentials.view()),
+                );
+                emit_event(
+                    app,
+                    AppEvent::Traffic(lock_state(&app_state).traffic.clone()),
+                );
+                emit_event(
+                    app,
+                    AppEvent::TrafficUnits(lock_state(&app_state).traffic_units.clone()),
+                );
+                emit_event(app, AppEvent::Quotas(lock_state(&app_state).quotas.clone()));
+                ui::show_main_window(app, true);
             }
             _ => {}
         })
-        .invoke_handler(tauri::generate_handler![credential_check])
+        .invoke_handler(tauri::generate_handler![
+            get_status,
+            credential_check,
+            save_credentials,
+            set_autolaunch,
+            minimise,
+            get_settings,
+            save_settings,
+            get_account_details,
+            list_sessions,
+            terminate_session,
+            change_password,
+            list_interfaces
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
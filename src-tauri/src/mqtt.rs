@@ -0,0 +1,359 @@
+use rumqttc::{Client, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::secret::Secret;
+
+/// Fields of `TrafficStats`, each published under `<base>/traffic/<field>`
+pub const TRAFFIC_FIELDS: [&str; 5] = ["total", "last", "current", "used", "remaining"];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub client_id: String,
+    /// Prefix of every topic published or subscribed to
+    pub base_topic: String,
+    /// Announce sensors and buttons to Home Assistant
+    pub discovery: bool,
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        MqttSettings {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            client_id: "autocampnet".to_string(),
+            base_topic: "autocampnet".to_string(),
+            discovery: true,
+            discovery_prefix: "homeassistant".to_string(),
+        }
+    }
+}
+
+impl MqttSettings {
+    fn topic(&self, suffix: &str) -> String {
+        format!("{}/{}", self.base_topic.trim_end_matches('/'), suffix)
+    }
+
+    /// Client id cut down to what Home Assistant accepts as a node id
+    fn node_id(&self) -> String {
+        self.client_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+/// Payloads accepted on `<base>/command`
+#[derive(Clone, Copy, PartialEq)]
+pub enum MqttCommand {
+    Login,
+    Logout,
+    Reconnect,
+}
+
+impl MqttCommand {
+    const ALL: [MqttCommand; 3] = [
+        MqttCommand::Login,
+        MqttCommand::Logout,
+        MqttCommand::Reconnect,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MqttCommand::Login => "login",
+            MqttCommand::Logout => "logout",
+            MqttCommand::Reconnect => "reconnect",
+        }
+    }
+
+    fn parse(payload: &str) -> Option<MqttCommand> {
+        MqttCommand::ALL
+            .into_iter()
+            .find(|command| command.name() == payload.trim().to_lowercase())
+    }
+}
+
+struct Connection {
+    client: Client,
+    settings: MqttSettings,
+    stopped: Arc<AtomicBool>,
+}
+
+/// Publishes connection state and usage to a broker. Everything published
+/// is retained, and kept here too so that it is sent again after the broker
+/// restarts.
+#[derive(Default)]
+pub struct Mqtt {
+    connection: Mutex<Option<Connection>>,
+    /// Payloads by topic suffix
+    retained: Arc<Mutex<BTreeMap<String, String>>>,
+    /// Units the traffic sensors were last announced with, in the order of
+    /// `TRAFFIC_FIELDS`
+    units: Arc<Mutex<[String; 5]>>,
+}
+
+impl Mqtt {
+    /// Connects with `settings`, replacing any earlier connection. Commands
+    /// are handed to `on_command` on a thread of their own.
+    pub fn start(
+        &self,
+        settings: &MqttSettings,
+        on_command: Arc<dyn Fn(MqttCommand) + Send + Sync>,
+    ) {
+        self.stop();
+        if !settings.enabled {
+            return;
+        }
+        let mut options = MqttOptions::new(&settings.client_id, &settings.host, settings.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &settings.username {
+            let password = settings.password.as_ref().map(Secret::expose);
+            options.set_credentials(username, password.unwrap_or_default());
+        }
+        options.set_last_will(LastWill::new(
+            settings.topic("availability"),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        let (client, mut connection) = Client::new(options, 64);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut thread_client = client.clone();
+        let thread_settings = settings.clone();
+        let thread_stopped = stopped.clone();
+        let retained = self.retained.clone();
+        let units = self.units.clone();
+        std::thread::spawn(move || {
+            for notification in connection.iter() {
+                // Stopping waits for the offline message and disconnect to
+                // go out first
+                if thread_stopped.load(Ordering::SeqCst)
+                    && matches!(
+                        notification,
+                        Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(_)
+                    )
+                {
+                    break;
+                }
+                match notification {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        let settings = &thread_settings;
+                        thread_client
+                            .try_subscribe(settings.topic("command"), QoS::AtLeastOnce)
+                            .ok();
+                        let mut messages = vec![(settings.topic("availability"), "online".into())];
+                        if settings.discovery {
+                            messages.extend(discovery(
+                                settings,
+                                &units.lock().unwrap_or_else(PoisonError::into_inner),
+                            ));
+                        }
+                        for (suffix, payload) in retained
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .iter()
+                        {
+                            messages.push((settings.topic(suffix), payload.to_string()));
+                        }
+                        for (topic, payload) in messages {
+                            thread_client
+                                .try_publish(topic, QoS::AtLeastOnce, true, payload)
+                                .ok();
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let command = std::str::from_utf8(&publish.payload)
+                            .ok()
+                            .and_then(MqttCommand::parse);
+                        if let Some(command) = command {
+                            let on_command = on_command.clone();
+                            std::thread::spawn(move || on_command(command));
+                        }
+                    }
+                    Ok(_) => {}
+                    // The next poll reconnects, wait a little so an absent
+                    // broker is not hammered
+                    Err(_) => std::thread::sleep(Duration::from_secs(5)),
+                }
+            }
+        });
+        *self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Connection {
+            client,
+            settings: settings.clone(),
+            stopped,
+        });
+    }
+
+    /// Marks the app offline and lets go of the broker
+    pub fn stop(&self) {
+        if let Some(mut connection) = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            connection
+                .client
+                .try_publish(
+                    connection.settings.topic("availability"),
+                    QoS::AtLeastOnce,
+                    true,
+                    "offline",
+                )
+                .ok();
+            connection.stopped.store(true, Ordering::SeqCst);
+            connection.client.try_disconnect().ok();
+        }
+    }
+
+    fn publish(&self, suffix: &str, payload: String) {
+        self.retained
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(suffix.to_string(), payload.to_string());
+        if let Some(connection) = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            let topic = connection.settings.topic(suffix);
+            connection
+                .client
+                .try_publish(topic, QoS::AtLeastOnce, true, payload)
+                .ok();
+        }
+    }
+
+    pub fn publish_state(&self, state: &str) {
+        self.publish("state", state.to_string());
+    }
+
+    /// Publishes `values` with their `units`, both in the order of
+    /// `TRAFFIC_FIELDS`, announcing the sensors again when a unit changed
+    pub fn publish_traffic(&self, values: [f32; 5], units: &[String; 5]) {
+        if *self.units.lock().unwrap_or_else(PoisonError::into_inner) != *units {
+            *self.units.lock().unwrap_or_else(PoisonError::into_inner) = units.clone();
+            if let Some(connection) = self
+                .connection
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_mut()
+            {
+                if connection.settings.discovery {
+                    for (topic, payload) in discovery(&connection.settings, units) {
+                        connection
+                            .client
+                            .try_publish(topic, QoS::AtLeastOnce, true, payload)
+                            .ok();
+                    }
+                }
+            }
+        }
+        for (field, value) in TRAFFIC_FIELDS.iter().zip(values) {
+            self.publish(&format!("traffic/{}", field), format!("{:.2}", value));
+        }
+    }
+}
+
+/// Home Assistant discovery topics and payloads: a sensor for the connection
+/// state and each traffic field, and a button for each command
+fn discovery(settings: &MqttSettings, units: &[String; 5]) -> Vec<(String, String)> {
+    let node_id = settings.node_id();
+    let prefix = settings.discovery_prefix.trim_end_matches('/');
+    let device = serde_json::json!({
+        "identifiers": [node_id],
+        "name": "Auto Campnet",
+        "manufacturer": "riskycase",
+        "model": "auto-campnet-gui",
+    });
+    let mut messages = vec![(
+        format!("{}/sensor/{}/state/config", prefix, node_id),
+        serde_json::json!({
+            "name": "Campnet connection",
+            "unique_id": format!("{}_state", node_id),
+            "state_topic": settings.topic("state"),
+            "availability_topic": settings.topic("availability"),
+            "icon": "mdi:lan-connect",
+            "device": device,
+        }),
+    )];
+    for (field, unit) in TRAFFIC_FIELDS.iter().zip(units) {
+        let mut config = serde_json::json!({
+            "name": format!("Campnet {} data", field),
+            "unique_id": format!("{}_{}", node_id, field),
+            "state_topic": settings.topic(&format!("traffic/{}", field)),
+            "availability_topic": settings.topic("availability"),
+            "icon": "mdi:gauge",
+            "device": device,
+        });
+        if !unit.is_empty() {
+            config["unit_of_measurement"] = unit.as_str().into();
+        }
+        messages.push((
+            format!("{}/sensor/{}/{}/config", prefix, node_id, field),
+            config,
+        ));
+    }
+    for command in MqttCommand::ALL {
+        messages.push((
+            format!("{}/button/{}/{}/config", prefix, node_id, command.name()),
+            serde_json::json!({
+                "name": format!("Campnet {}", command.name()),
+                "unique_id": format!("{}_{}", node_id, command.name()),
+                "command_topic": settings.topic("command"),
+                "payload_press": command.name(),
+                "availability_topic": settings.topic("availability"),
+                "device": device,
+            }),
+        ));
+    }
+    messages
+        .into_iter()
+        .map(|(topic, payload)| (topic, payload.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_traffic_sensor_gets_its_own_unit() {
+        let units = ["GB", "GB", "MB", "MB", ""].map(String::from);
+        let configs: BTreeMap<String, serde_json::Value> =
+            discovery(&MqttSettings::default(), &units)
+                .into_iter()
+                .map(|(topic, payload)| (topic, serde_json::from_str(&payload).unwrap()))
+                .collect();
+        let unit_of = |field: &str| {
+            configs
+                .iter()
+                .find(|(topic, _)| topic.ends_with(&format!("/{}/config", field)))
+                .map(|(_, config)| config["unit_of_measurement"].clone())
+                .unwrap()
+        };
+        assert_eq!(unit_of("total"), "GB");
+        assert_eq!(unit_of("current"), "MB");
+        assert_eq!(unit_of("used"), "MB");
+        // Left out rather than announced as an empty unit
+        assert_eq!(unit_of("remaining"), serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,298 @@
+use regex::Regex;
+use reqwest::blocking::{Client, RequestBuilder};
+
+use super::{decode, timestamp, PortalBackend, PortalError, PortalSession};
+use crate::account::{self, AccountDetails};
+use crate::quota::{self, Quota};
+use crate::sessions::{self, ActiveSession};
+
+/// The JSP user portal of older firmware, driven through form posts to
+/// `/userportal/Controller` and scraped pages under `myaccount/`.
+pub struct LegacyPortal;
+
+pub fn probe(client: &Client, endpoint: &str) -> bool {
+    client
+        .get(endpoint.to_string() + "/userportal/webpages/myaccount/login.jsp")
+        .send()
+        .map(|response| response.status().is_success())
+        .unwrap_or(false)
+}
+
+/// Sends a request with the session. An expired session shows up as a
+/// redirect to the login page or as a response without `marker`.
+fn send(
+    request: RequestBuilder,
+    endpoint: &str,
+    session: &PortalSession,
+    marker: &str,
+) -> Result<String, PortalError> {
+    let response = request
+        .header("X-CSRF-Token", session.token.to_string())
+        .header(reqwest::header::COOKIE, session.cookie.to_string())
+        .header(
+            reqwest::header::REFERER,
+            endpoint.to_string() + "/userportal/webpages/myaccount/login.jsp",
+        )
+        .send()
+        .map_err(|_| PortalError::Unavailable)?;
+    let redirected_to_login = response.url().path().ends_with("login.jsp");
+    let status_ok = response.status().is_success();
+    let body = response.text().map_err(|_| PortalError::Unavailable)?;
+    if !redirected_to_login && status_ok && body.contains(marker) {
+        Ok(body)
+    } else {
+        Err(PortalError::Expired)
+    }
+}
+
+fn page(
+    client: &Client,
+    endpoint: &str,
+    session: &PortalSession,
+    path: &str,
+    marker: &str,
+) -> Result<String, PortalError> {
+    let request = client
+        .get(endpoint.to_string() + path)
+        .query(&[("popup", "0".to_string()), ("t", timestamp().to_string())]);
+    send(request, endpoint, session, marker)
+}
+
+/// Posts a form to the controller, which answers with a JSON status. A
+/// page without one is the login page of an expired session.
+fn post(
+    client: &Client,
+    endpoint: &str,
+    session: &PortalSession,
+    form: &[(&str, String)],
+) -> Result<(), PortalError> {
+    let request = client
+        .post(endpoint.to_string() + "/userportal/Controller")
+        .form(form);
+    if status_ok(&send(request, endpoint, session, "\"status\"")?) {
+        Ok(())
+    } else {
+        Err(PortalError::Rejected)
+    }
+}
+
+fn get_cookie(
+    client: &Client,
+    endpoint: &str,
+    username: &str,
+    password: &str,
+) -> Result<String, PortalError> {
+    let json = serde_json::json!({
+        "username": decode(username),
+        "password": decode(password),
+        "languageid": "1",
+        "browser": "Chrome_109",
+    });
+    let response = client
+        .post(endpoint.to_string() + "/userportal/Controller")
+        .form(&[
+            ("mode", "451".to_string()),
+            ("json", json.to_string()),
+            ("t", timestamp().to_string()),
+        ])
+        .send()
+        .map_err(|_| PortalError::Unavailable)?;
+    // The portal leaves out the cookie when it rejects the login
+    response
+        .headers()
+        .get(reqwest::header::SET_COOKIE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.split(';').next())
+        .map(|cookie| cookie.to_string())
+        .ok_or(PortalError::Rejected)
+}
+
+fn get_csrf(client: &Client, endpoint: &str, cookie: &str) -> Result<String, PortalError> {
+    let body = client
+        .get(endpoint.to_string() + "/userportal/webpages/myaccount/index.jsp")
+        .header(reqwest::header::COOKIE, cookie.to_string())
+        .send()
+        .and_then(|response| response.text())
+        .map_err(|_| PortalError::Unavailable)?;
+    let regex = Regex::new(r"k3n = '(.+)'").unwrap();
+    regex
+        .captures(body.as_str())
+        .and_then(|matches| matches.get(0))
+        .and_then(|csrf| csrf.as_str().split('\'').nth(1))
+        .map(|csrf| csrf.to_string())
+        .ok_or(PortalError::Unexpected)
+}
+
+/// The controller answers form posts with a JSON status
+fn status_ok(body: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| json["status"].as_i64())
+        == Some(200)
+}
+
+impl PortalBackend for LegacyPortal {
+    fn login(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<PortalSession, PortalError> {
+        let cookie = get_cookie(client, endpoint, username, password)?;
+        let token = get_csrf(client, endpoint, &cookie)?;
+        Ok(PortalSession { cookie, token })
+    }
+
+    fn quotas(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<Vec<Quota>, PortalError> {
+        let body = page(
+            client,
+            endpoint,
+            session,
+            "/userportal/webpages/myaccount/AccountStatus.jsp",
+            "content3",
+        )?;
+        let dom = tl::parse(body.as_str(), tl::ParserOptions::default())
+            .map_err(|_| PortalError::Unexpected)?;
+        let parser = dom.parser();
+        let table = dom
+            .get_element_by_id("content3")
+            .and_then(|element| element.get(parser))
+            .map(|element| element.inner_html(parser).to_string())
+            .ok_or(PortalError::Unexpected)?;
+        // Every account has a policy, a page without one to read has most
+        // likely changed with a firmware upgrade
+        let quotas = quota::parse_quotas(table.as_str());
+        if quotas.is_empty() {
+            Err(PortalError::Unexpected)
+        } else {
+            Ok(quotas)
+        }
+    }
+
+    /// The plan details are spread over the account overview and the
+    /// account status page
+    fn account_details(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<AccountDetails, PortalError> {
+        let mut details = account::parse_account_details(
+            page(
+                client,
+                endpoint,
+                session,
+                "/userportal/webpages/myaccount/index.jsp",
+                "k3n",
+            )?
+            .as_str(),
+        );
+        let status_page = page(
+            client,
+            endpoint,
+            session,
+            "/userportal/webpages/myaccount/AccountStatus.jsp",
+            "content3",
+        );
+        if let Ok(status_page) = status_page {
+            details.merge(account::parse_account_details(status_page.as_str()));
+        }
+        Ok(details)
+    }
+
+    fn sessions(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<Vec<ActiveSession>, PortalError> {
+        page(
+            client,
+            endpoint,
+            session,
+            "/userportal/webpages/myaccount/LiveUsers.jsp",
+            "<table",
+        )
+        .map(|page| sessions::parse_sessions(page.as_str()))
+    }
+
+    fn terminate_session(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+        id: &str,
+    ) -> Result<(), PortalError> {
+        post(
+            client,
+            endpoint,
+            session,
+            &[
+                ("mode", "462".to_string()),
+                ("sessionids", id.to_string()),
+                ("t", timestamp().to_string()),
+            ],
+        )
+    }
+
+    fn change_password(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+        username: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), PortalError> {
+        let json = serde_json::json!({
+            "username": decode(username),
+            "oldpassword": decode(current_password),
+            "newpassword": decode(new_password),
+        });
+        post(
+            client,
+            endpoint,
+            session,
+            &[
+                ("mode", "453".to_string()),
+                ("json", json.to_string()),
+                ("t", timestamp().to_string()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{Canned, FixtureServer};
+
+    fn quotas(status_page: &str) -> Result<Vec<Quota>, PortalError> {
+        let server = FixtureServer::start(&[(
+            "GET /userportal/webpages/myaccount/AccountStatus.jsp",
+            Canned::ok(status_page),
+        )]);
+        LegacyPortal.quotas(&Client::new(), &server.endpoint, &PortalSession::default())
+    }
+
+    #[test]
+    fn reads_quotas_from_the_status_page() {
+        let page = format!(
+            r#"<div id="content3">{}</div>"#,
+            include_str!("../../fixtures/legacy_status.html")
+        );
+        assert_eq!(quotas(&page).ok().map(|quotas| quotas.len()), Some(1));
+    }
+
+    #[test]
+    fn status_page_without_quotas_is_unexpected() {
+        let page = r#"<div id="content3"><p>Your account</p></div>"#;
+        assert!(quotas(page).err() == Some(PortalError::Unexpected));
+    }
+}
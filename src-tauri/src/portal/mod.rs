@@ -0,0 +1,107 @@
+use reqwest::blocking::Client;
+use std::sync::Arc;
+
+use crate::account::AccountDetails;
+use crate::quota::Quota;
+use crate::sessions::ActiveSession;
+
+mod legacy;
+mod modern;
+
+/// What a user portal login leaves behind to authenticate later requests
+#[derive(Clone, Default)]
+pub struct PortalSession {
+    pub cookie: String,
+    /// CSRF token sent along with every request
+    pub token: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PortalError {
+    /// The session is no longer accepted, logging in again may help
+    Expired,
+    /// The portal answered but refused the request
+    Rejected,
+    /// The request did not go through
+    Unavailable,
+    /// The answer was not what this backend expects, the portal may have
+    /// been upgraded to a different version
+    Unexpected,
+}
+
+/// One version of the Sophos user portal. Credentials are passed URL
+/// encoded, the same way they are saved.
+pub trait PortalBackend: Send + Sync {
+    fn login(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<PortalSession, PortalError>;
+
+    fn quotas(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<Vec<Quota>, PortalError>;
+
+    fn account_details(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<AccountDetails, PortalError>;
+
+    fn sessions(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<Vec<ActiveSession>, PortalError>;
+
+    fn terminate_session(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+        id: &str,
+    ) -> Result<(), PortalError>;
+
+    fn change_password(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+        username: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), PortalError>;
+}
+
+/// Works out which portal is running at `endpoint`. The JSON API of newer
+/// firmware is tried first since older firmware simply 404s its paths.
+pub fn detect(client: &Client, endpoint: &str) -> Option<Arc<dyn PortalBackend>> {
+    if modern::probe(client, endpoint) {
+        Some(Arc::new(modern::ModernPortal))
+    } else if legacy::probe(client, endpoint) {
+        Some(Arc::new(legacy::LegacyPortal))
+    } else {
+        None
+    }
+}
+
+/// Saved credentials are URL encoded, the portals want them as typed
+fn decode(value: &str) -> String {
+    percent_encoding::percent_decode_str(value)
+        .decode_utf8_lossy()
+        .to_string()
+}
+
+fn timestamp() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
@@ -0,0 +1,429 @@
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{decode, PortalBackend, PortalError, PortalSession};
+use crate::account::AccountDetails;
+use crate::quota::{self, Quota, QuotaKind, QuotaPeriod};
+use crate::sessions::ActiveSession;
+
+/// The JSON user portal of newer SFOS releases, served under
+/// `/userportal/api/`.
+///
+/// Experimental: Sophos does not document this API. The endpoints and
+/// response shapes are read off a portal in use and only tested against
+/// the recorded fixtures, so any of it may differ between firmware
+/// versions. Answers it cannot read are `Unexpected`, which makes the app
+/// detect the backend again.
+pub struct ModernPortal;
+
+pub fn probe(client: &Client, endpoint: &str) -> bool {
+    client
+        .get(endpoint.to_string() + "/userportal/api/version")
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .ok()
+        .filter(|response| response.status().is_success())
+        .and_then(|response| response.json::<serde_json::Value>().ok())
+        .map(|version| version.get("version").is_some())
+        .unwrap_or(false)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginResponse {
+    status: i64,
+    csrf_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaResponse {
+    /// `data`, `upload`, `download` or `time`
+    #[serde(rename = "type")]
+    kind: String,
+    /// `daily`, `weekly`, `monthly`, `yearly`, `cyclic` or `total`
+    cycle: String,
+    allotted: f32,
+    used: f32,
+    remaining: Option<f32>,
+    #[serde(default)]
+    unit: String,
+    reset_time: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QuotasResponse {
+    quotas: Vec<QuotaResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileResponse {
+    group: Option<String>,
+    policy: Option<String>,
+    expiry_date: Option<String>,
+    bandwidth: Option<String>,
+    upload_bandwidth: Option<String>,
+    download_bandwidth: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionResponse {
+    id: String,
+    ip: Option<String>,
+    mac: Option<String>,
+    login_time: Option<String>,
+    data_used: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SessionsResponse {
+    sessions: Vec<SessionResponse>,
+}
+
+fn with_session(request: RequestBuilder, session: &PortalSession) -> RequestBuilder {
+    request
+        .header("X-CSRF-Token", session.token.to_string())
+        .header(reqwest::header::COOKIE, session.cookie.to_string())
+        .header(reqwest::header::ACCEPT, "application/json")
+}
+
+/// For requests made with a session, which a 401 or 403 means has expired
+fn check(response: Response) -> Result<Response, PortalError> {
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(PortalError::Expired),
+        StatusCode::NOT_FOUND => Err(PortalError::Unexpected),
+        status if status.is_success() => Ok(response),
+        _ => Err(PortalError::Rejected),
+    }
+}
+
+fn get<T: DeserializeOwned>(
+    client: &Client,
+    endpoint: &str,
+    session: &PortalSession,
+    path: &str,
+) -> Result<T, PortalError> {
+    let response = with_session(client.get(endpoint.to_string() + path), session)
+        .send()
+        .map_err(|_| PortalError::Unavailable)?;
+    check(response)?
+        .json::<T>()
+        .map_err(|_| PortalError::Unexpected)
+}
+
+fn post(
+    client: &Client,
+    endpoint: &str,
+    session: &PortalSession,
+    path: &str,
+    body: serde_json::Value,
+) -> Result<(), PortalError> {
+    let response = with_session(client.post(endpoint.to_string() + path), session)
+        .json(&body)
+        .send()
+        .map_err(|_| PortalError::Unavailable)?;
+    check(response).map(|_| ())
+}
+
+impl PortalBackend for ModernPortal {
+    fn login(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<PortalSession, PortalError> {
+        let response = client
+            .post(endpoint.to_string() + "/userportal/api/login")
+            .json(&serde_json::json!({
+                "username": decode(username),
+                "password": decode(password),
+            }))
+            .send()
+            .map_err(|_| PortalError::Unavailable)?;
+        let cookie = response
+            .headers()
+            .get(reqwest::header::SET_COOKIE)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.split(';').next())
+            .map(|cookie| cookie.to_string());
+        // Without a session yet, a refusal means the credentials are wrong
+        let response = match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(PortalError::Rejected),
+            _ => check(response),
+        }?;
+        let login: LoginResponse = response.json().map_err(|_| PortalError::Unexpected)?;
+        match (login.status, cookie, login.csrf_token) {
+            (200, Some(cookie), Some(token)) => Ok(PortalSession { cookie, token }),
+            _ => Err(PortalError::Rejected),
+        }
+    }
+
+    fn quotas(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<Vec<Quota>, PortalError> {
+        let response: QuotasResponse = get(
+            client,
+            endpoint,
+            session,
+            "/userportal/api/myaccount/quotas",
+        )?;
+        Ok(response
+            .quotas
+            .into_iter()
+            .map(|quota| {
+                let (kind, period) = quota::describe(&format!("{} {}", quota.cycle, quota.kind))
+                    .unwrap_or((QuotaKind::Data, QuotaPeriod::Total));
+                Quota {
+                    kind,
+                    period,
+                    limit: quota.allotted,
+                    used: quota.used,
+                    remaining: quota.remaining.unwrap_or(quota.allotted - quota.used),
+                    unit: quota.unit,
+                    resets_at: quota.reset_time,
+                    legacy: None,
+                }
+            })
+            .collect())
+    }
+
+    fn account_details(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<AccountDetails, PortalError> {
+        let profile: ProfileResponse = get(
+            client,
+            endpoint,
+            session,
+            "/userportal/api/myaccount/profile",
+        )?;
+        Ok(AccountDetails {
+            group: profile.group,
+            policy: profile.policy,
+            expiry: profile.expiry_date,
+            bandwidth: profile.bandwidth,
+            upload: profile.upload_bandwidth,
+            download: profile.download_bandwidth,
+        })
+    }
+
+    fn sessions(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+    ) -> Result<Vec<ActiveSession>, PortalError> {
+        let response: SessionsResponse = get(
+            client,
+            endpoint,
+            session,
+            "/userportal/api/myaccount/sessions",
+        )?;
+        Ok(response
+            .sessions
+            .into_iter()
+            .map(|session| ActiveSession {
+                id: session.id,
+                ip: session.ip,
+                mac: session.mac,
+                start_time: session.login_time,
+                data_used: session.data_used,
+            })
+            .collect())
+    }
+
+    fn terminate_session(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+        id: &str,
+    ) -> Result<(), PortalError> {
+        post(
+            client,
+            endpoint,
+            session,
+            "/userportal/api/myaccount/sessions/logout",
+            serde_json::json!({ "ids": [id] }),
+        )
+    }
+
+    fn change_password(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        session: &PortalSession,
+        _username: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), PortalError> {
+        post(
+            client,
+            endpoint,
+            session,
+            "/userportal/api/myaccount/password",
+            serde_json::json!({
+                "oldPassword": decode(current_password),
+                "newPassword": decode(new_password),
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{Canned, FixtureServer};
+
+    fn session() -> PortalSession {
+        PortalSession {
+            cookie: "SESSION=abc".to_string(),
+            token: "csrf".to_string(),
+        }
+    }
+
+    fn login(server: &FixtureServer) -> Result<PortalSession, PortalError> {
+        ModernPortal.login(
+            &Client::new(),
+            &server.endpoint,
+            "f2026%40goa",
+            "p%26ss%22word",
+        )
+    }
+
+    #[test]
+    fn login_keeps_cookie_and_token() {
+        let server = FixtureServer::start(&[(
+            "POST /userportal/api/login",
+            Canned::ok(r#"{"status": 200, "csrfToken": "t0ken"}"#)
+                .header("Set-Cookie", "SESSION=abc; Path=/; HttpOnly"),
+        )]);
+        let session = login(&server).ok().unwrap();
+        assert_eq!(session.cookie, "SESSION=abc");
+        assert_eq!(session.token, "t0ken");
+        // Credentials are sent decoded
+        let body: serde_json::Value = serde_json::from_str(&server.received()[0].body).unwrap();
+        assert_eq!(body["username"], "f2026@goa");
+        assert_eq!(body["password"], "p&ss\"word");
+    }
+
+    #[test]
+    fn refused_login_is_rejected() {
+        let server = FixtureServer::start(&[(
+            "POST /userportal/api/login",
+            Canned::ok(r#"{"status": 401}"#),
+        )]);
+        assert!(login(&server).err() == Some(PortalError::Rejected));
+    }
+
+    #[test]
+    fn unauthorised_login_is_rejected() {
+        for status in [401, 403] {
+            let server = FixtureServer::start(&[(
+                "POST /userportal/api/login",
+                Canned::status(status, r#"{"status": 401}"#),
+            )]);
+            assert!(login(&server).err() == Some(PortalError::Rejected));
+        }
+    }
+
+    #[test]
+    fn unreadable_login_is_unexpected() {
+        let server = FixtureServer::start(&[(
+            "POST /userportal/api/login",
+            Canned::ok("<html>Sophos User Portal</html>"),
+        )]);
+        assert!(login(&server).err() == Some(PortalError::Unexpected));
+        let server = FixtureServer::start(&[]);
+        assert!(login(&server).err() == Some(PortalError::Unexpected));
+    }
+
+    #[test]
+    fn reads_quotas() {
+        let server = FixtureServer::start(&[(
+            "GET /userportal/api/myaccount/quotas",
+            Canned::ok(include_str!("../../fixtures/modern_quotas.json")),
+        )]);
+        let quotas = ModernPortal
+            .quotas(&Client::new(), &server.endpoint, &session())
+            .ok()
+            .unwrap();
+        assert_eq!(quotas.len(), 2);
+        assert!(quotas[0].kind == QuotaKind::Data && quotas[0].period == QuotaPeriod::Daily);
+        assert_eq!(quotas[0].remaining, 1535.5);
+        assert_eq!(
+            quotas[0].resets_at.as_deref(),
+            Some("2026-10-20T00:00:00+05:30")
+        );
+        assert!(quotas[1].kind == QuotaKind::Time && quotas[1].period == QuotaPeriod::Monthly);
+        assert_eq!(quotas[1].remaining, 4470.0);
+        let request = &server.received()[0];
+        assert_eq!(request.headers["x-csrf-token"], "csrf");
+        assert_eq!(request.headers["cookie"], "SESSION=abc");
+    }
+
+    #[test]
+    fn reads_profile() {
+        let server = FixtureServer::start(&[(
+            "GET /userportal/api/myaccount/profile",
+            Canned::ok(include_str!("../../fixtures/modern_profile.json")),
+        )]);
+        let details = ModernPortal
+            .account_details(&Client::new(), &server.endpoint, &session())
+            .ok()
+            .unwrap();
+        assert_eq!(details.policy.as_deref(), Some("Student Daily 2GB"));
+        assert_eq!(details.expiry.as_deref(), Some("2027-06-30"));
+        assert_eq!(details.upload, None);
+    }
+
+    #[test]
+    fn lists_and_terminates_sessions() {
+        let server = FixtureServer::start(&[
+            (
+                "GET /userportal/api/myaccount/sessions",
+                Canned::ok(include_str!("../../fixtures/modern_sessions.json")),
+            ),
+            (
+                "POST /userportal/api/myaccount/sessions/logout",
+                Canned::ok(""),
+            ),
+        ]);
+        let client = Client::new();
+        let sessions = ModernPortal
+            .sessions(&client, &server.endpoint, &session())
+            .ok()
+            .unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(
+            sessions[0].start_time.as_deref(),
+            Some("2026-10-19T09:12:00+05:30")
+        );
+        assert_eq!(sessions[1].mac, None);
+        assert!(ModernPortal
+            .terminate_session(&client, &server.endpoint, &session(), "s-8822")
+            .is_ok());
+        let body: serde_json::Value = serde_json::from_str(&server.received()[1].body).unwrap();
+        assert_eq!(body, serde_json::json!({ "ids": ["s-8822"] }));
+    }
+
+    #[test]
+    fn refused_session_has_expired() {
+        let server = FixtureServer::start(&[(
+            "GET /userportal/api/myaccount/quotas",
+            Canned::status(401, ""),
+        )]);
+        let quotas = ModernPortal.quotas(&Client::new(), &server.endpoint, &session());
+        assert!(quotas.err() == Some(PortalError::Expired));
+    }
+}
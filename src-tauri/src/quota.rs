@@ -0,0 +1,569 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaKind {
+    Data,
+    Upload,
+    Download,
+    /// Surfing time, counted in minutes
+    Time,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    /// Custom cycle length set by the administrator
+    Cyclic,
+    /// Never resets
+    Total,
+}
+
+/// One limit the portal enforces on the account
+#[derive(Serialize, Deserialize, Clone, TS)]
+pub struct Quota {
+    pub kind: QuotaKind,
+    pub period: QuotaPeriod,
+    pub limit: f32,
+    pub used: f32,
+    pub remaining: f32,
+    pub unit: String,
+    pub resets_at: Option<String>,
+    /// Only set for the fixed layout of older portals
+    #[serde(skip)]
+    pub legacy: Option<LegacyTraffic>,
+}
+
+/// The five figures older portals show for their single daily policy, in
+/// the order total, last cycle, current cycle, used and remaining. Each
+/// comes in its own unit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegacyTraffic {
+    pub values: [f32; 5],
+    pub units: [String; 5],
+}
+
+impl Quota {
+    /// What runs out, as used in the usage warnings
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            QuotaKind::Data => "data",
+            QuotaKind::Upload => "upload",
+            QuotaKind::Download => "download",
+            QuotaKind::Time => "surfing time",
+        }
+    }
+
+    /// Amount in this quota's unit, scaled to a readable unit for data
+    /// quotas, as in `3.2 GB`
+    pub fn format_amount(&self, amount: f32) -> String {
+        let mut amount = match megabytes(&self.unit) {
+            Some(scale) => amount * scale,
+            None => return format!("{:.0} {}", amount, self.unit),
+        };
+        let mut unit = "MB";
+        for larger in ["GB", "TB"] {
+            if amount < 1024.0 {
+                break;
+            }
+            amount /= 1024.0;
+            unit = larger;
+        }
+        format!("{:.1} {}", amount, unit)
+    }
+
+    /// When the quota resets, for the formats portals are known to show it
+    /// in. Times without a zone are taken as local.
+    pub fn reset_time(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        let resets_at = self.resets_at.as_deref()?.trim();
+        if let Ok(at) = chrono::DateTime::parse_from_rfc3339(resets_at) {
+            return Some(at.with_timezone(&chrono::Local));
+        }
+        let naive = chrono::NaiveDateTime::parse_from_str(resets_at, "%d %b %Y %H:%M")
+            .or_else(|_| {
+                chrono::NaiveDate::parse_from_str(resets_at, "%d %b %Y")
+                    .map(|date| date.and_time(chrono::NaiveTime::MIN))
+            })
+            .ok()?;
+        chrono::TimeZone::from_local_datetime(&chrono::Local, &naive).earliest()
+    }
+
+    /// Fraction of the quota used up, 0 for quotas without a limit
+    pub fn usage(&self) -> f32 {
+        if self.limit > 0.0 {
+            self.used / self.limit
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The quota closest to running out, which is the one that decides when
+/// the account gets cut off.
+pub fn most_constrained(quotas: &[Quota]) -> Option<&Quota> {
+    quotas
+        .iter()
+        .filter(|quota| quota.limit > 0.0)
+        .max_by(|a, b| a.usage().total_cmp(&b.usage()))
+}
+
+/// Kind and period of a quota from how the portal names it, as in
+/// `Daily Data Transfer`
+pub fn describe(label: &str) -> Option<(QuotaKind, QuotaPeriod)> {
+    let label = label.to_lowercase();
+    let kind = if label.contains("upload") {
+        QuotaKind::Upload
+    } else if label.contains("download") {
+        QuotaKind::Download
+    } else if label.contains("time") || label.contains("surfing") {
+        QuotaKind::Time
+    } else if label.contains("data") || label.contains("transfer") {
+        QuotaKind::Data
+    } else {
+        return None;
+    };
+    let period = if label.contains("daily") || label.contains("day") {
+        QuotaPeriod::Daily
+    } else if label.contains("week") {
+        QuotaPeriod::Weekly
+    } else if label.contains("month") {
+        QuotaPeriod::Monthly
+    } else if label.contains("year") {
+        QuotaPeriod::Yearly
+    } else if label.contains("cycl") {
+        QuotaPeriod::Cyclic
+    } else {
+        QuotaPeriod::Total
+    };
+    Some((kind, period))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Limit,
+    Used,
+    Remaining,
+    ResetsAt,
+    Other,
+}
+
+fn field(label: &str) -> Field {
+    let label = label.to_lowercase();
+    if label.contains("remain") || label.contains("balance") || label.contains("left") {
+        Field::Remaining
+    } else if label.contains("used") || label.contains("consumed") {
+        Field::Used
+    } else if label.contains("reset") || label.contains("renew") || label.contains("cycle end") {
+        Field::ResetsAt
+    } else if label.contains("allot") || label.contains("limit") || label.contains("total") {
+        Field::Limit
+    } else {
+        Field::Other
+    }
+}
+
+fn text(node: &tl::Node, parser: &tl::Parser) -> String {
+    node.inner_text(parser)
+        .replace("&nbsp;", " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn is_tag(node: &tl::Node, name: &[u8]) -> bool {
+    node.as_tag()
+        .map(|tag| tag.name().as_bytes().eq_ignore_ascii_case(name))
+        .unwrap_or(false)
+}
+
+/// The portal shows the unit either as text after the number or as an
+/// image named after it, as in `<img src="images/GB.gif">`.
+fn unit_image(node: &tl::Node, parser: &tl::Parser) -> Option<String> {
+    node.children()?
+        .all(parser)
+        .iter()
+        .filter_map(|child| child.as_tag())
+        .filter_map(|tag| tag.attributes().get("src").flatten())
+        .map(|src| src.as_utf8_str().to_string())
+        .find_map(|src| {
+            src.rsplit('/')
+                .next()
+                .and_then(|file| file.split('.').next())
+                .map(|unit| unit.to_string())
+        })
+}
+
+/// Reads a number with its unit from a cell. Surfing time shown as
+/// `hh:mm[:ss]` is turned into minutes.
+/// A number as the portal shows it. `NaN` and `inf` parse as floats but are
+/// never real amounts, they would break comparing quotas.
+fn finite_number(text: &str) -> Option<f32> {
+    text.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite())
+}
+
+fn amount(node: &tl::Node, parser: &tl::Parser) -> Option<(f32, String)> {
+    let value = text(node, parser);
+    if value.contains(':') {
+        let parts: Vec<f32> = value
+            .split(':')
+            .map(finite_number)
+            .collect::<Option<Vec<f32>>>()?;
+        let minutes = parts[0] * 60.0
+            + parts.get(1).copied().unwrap_or(0.0)
+            + parts.get(2).copied().unwrap_or(0.0) / 60.0;
+        return Some((minutes, "min".to_string()));
+    }
+    let number: String = value
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .filter(|c| *c != ',')
+        .collect();
+    let number = finite_number(&number)?;
+    let unit = value
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ',')
+        .trim()
+        .to_string();
+    let unit = if unit.is_empty() {
+        unit_image(node, parser).unwrap_or_default()
+    } else {
+        unit
+    };
+    Some((number, unit))
+}
+
+/// Size of a data unit in megabytes, the portal mixes units between columns
+fn megabytes(unit: &str) -> Option<f32> {
+    match unit.to_uppercase().as_str() {
+        "B" | "BYTES" => Some(1.0 / 1024.0 / 1024.0),
+        "KB" => Some(1.0 / 1024.0),
+        "MB" => Some(1.0),
+        "GB" => Some(1024.0),
+        "TB" => Some(1024.0 * 1024.0),
+        _ => None,
+    }
+}
+
+struct PartialQuota {
+    kind: QuotaKind,
+    period: QuotaPeriod,
+    limit: Option<f32>,
+    used: Option<f32>,
+    remaining: Option<f32>,
+    unit: String,
+    resets_at: Option<String>,
+}
+
+impl PartialQuota {
+    fn new((kind, period): (QuotaKind, QuotaPeriod)) -> Self {
+        PartialQuota {
+            kind,
+            period,
+            limit: None,
+            used: None,
+            remaining: None,
+            unit: "".to_string(),
+            resets_at: None,
+        }
+    }
+
+    fn set(&mut self, field: Field, cell: &tl::Node, parser: &tl::Parser) {
+        if field == Field::ResetsAt {
+            let value = text(cell, parser);
+            if !value.is_empty() {
+                self.resets_at = Some(value);
+            }
+            return;
+        }
+        let value = amount(cell, parser);
+        if value.is_none() {
+            return;
+        }
+        let (number, unit) = value.unwrap();
+        let number = if self.unit.is_empty() {
+            self.unit = unit;
+            number
+        } else {
+            // Keep every amount in the unit the first one came in
+            match (megabytes(&unit), megabytes(&self.unit)) {
+                (Some(from), Some(to)) => number * from / to,
+                _ => number,
+            }
+        };
+        match field {
+            Field::Limit => self.limit = Some(number),
+            Field::Used => self.used = Some(number),
+            Field::Remaining => self.remaining = Some(number),
+            _ => {}
+        }
+    }
+
+    /// Fills in used or remaining from the other, the portal does not always
+    /// show both
+    fn finish(self) -> Option<Quota> {
+        let limit = self.limit?;
+        let used = self
+            .used
+            .or_else(|| self.remaining.map(|remaining| limit - remaining))?;
+        let remaining = self.remaining.unwrap_or(limit - used);
+        Some(Quota {
+            kind: self.kind,
+            period: self.period,
+            limit,
+            used,
+            remaining,
+            unit: self.unit,
+            resets_at: self.resets_at,
+            legacy: None,
+        })
+    }
+}
+
+/// Reads every quota listed in the account status table. Two layouts are
+/// understood: one policy per row under a header naming the columns, and a
+/// heading per policy followed by label/value rows. Older portals that show
+/// a single policy as bare numbers fall back to the fixed layout this app
+/// has always read.
+pub fn parse_quotas(table: &str) -> Vec<Quota> {
+    let mut quotas: Vec<Quota> = Vec::new();
+    let dom = tl::parse(table, tl::ParserOptions::default());
+    if dom.is_err() {
+        return quotas;
+    }
+    let dom = dom.unwrap();
+    let parser = dom.parser();
+    let mut columns: Vec<Field> = Vec::new();
+    let mut section: Option<PartialQuota> = None;
+    if let Some(rows) = dom.query_selector("tr") {
+        for row in rows {
+            let row = row.get(parser).and_then(|node| node.as_tag());
+            if row.is_none() {
+                continue;
+            }
+            let cells: Vec<&tl::Node> = row
+                .unwrap()
+                .children()
+                .top()
+                .iter()
+                .filter_map(|child| child.get(parser))
+                .filter(|child| is_tag(child, b"td") || is_tag(child, b"th"))
+                .collect();
+            if cells.is_empty() {
+                continue;
+            }
+            let label = text(cells[0], parser);
+            let header_fields: Vec<Field> = cells
+                .iter()
+                .map(|cell| field(&text(cell, parser)))
+                .collect();
+            if cells.iter().any(|cell| is_tag(cell, b"th"))
+                && header_fields.iter().filter(|f| **f != Field::Other).count() >= 2
+            {
+                columns = header_fields;
+                continue;
+            }
+            match (describe(&label), section.as_mut()) {
+                (Some(described), _) if !columns.is_empty() && cells.len() > 1 => {
+                    let mut quota = PartialQuota::new(described);
+                    for (cell, column) in cells.iter().zip(columns.iter()).skip(1) {
+                        quota.set(*column, cell, parser);
+                    }
+                    quotas.extend(quota.finish());
+                }
+                (Some(described), _) if field(&label) == Field::Other => {
+                    if let Some(previous) = section.take() {
+                        quotas.extend(previous.finish());
+                    }
+                    section = Some(PartialQuota::new(described));
+                }
+                (_, Some(current)) if cells.len() > 1 => {
+                    current.set(field(&label), cells[1], parser);
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some(last) = section.take() {
+        quotas.extend(last.finish());
+    }
+    if quotas.is_empty() {
+        quotas.extend(legacy_quota(&dom));
+    }
+    quotas
+}
+
+/// Single daily data policy as numbers at fixed positions of `td.tabletext`,
+/// with the unit in the class of the element following each number
+fn legacy_quota(dom: &tl::VDom) -> Option<Quota> {
+    let parser = dom.parser();
+    let mut values: Vec<f32> = Vec::new();
+    let mut units: Vec<String> = Vec::new();
+    for cell in dom.query_selector("td.tabletext")? {
+        let cell = cell.get(parser)?;
+        values.push(finite_number(
+            &cell.inner_text(parser).replace("&nbsp;", ""),
+        )?);
+        units.push(
+            cell.children()?
+                .all(parser)
+                .get(1)?
+                .outer_html(parser)
+                .split('.')
+                .nth(1)?
+                .split('"')
+                .next()?
+                .to_string(),
+        );
+    }
+    if values.len() < 11 {
+        return None;
+    }
+    let legacy = LegacyTraffic {
+        values: [values[6], values[7], values[8], values[9], values[10]],
+        units: [
+            units[6].to_string(),
+            units[7].to_string(),
+            units[8].to_string(),
+            units[9].to_string(),
+            units[10].to_string(),
+        ],
+    };
+    // The limit and usage are compared in the unit of the remaining amount
+    let unit = legacy.units[4].to_string();
+    let convert = |index: usize| match (megabytes(&legacy.units[index]), megabytes(&unit)) {
+        (Some(from), Some(to)) => legacy.values[index] * from / to,
+        _ => legacy.values[index],
+    };
+    Some(Quota {
+        kind: QuotaKind::Data,
+        period: QuotaPeriod::Daily,
+        limit: convert(0),
+        used: convert(3),
+        remaining: convert(4),
+        unit,
+        resets_at: None,
+        legacy: Some(legacy),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_one_policy_per_row() {
+        let quotas = parse_quotas(include_str!("../fixtures/quota_rows.html"));
+        assert_eq!(quotas.len(), 2);
+        let data = &quotas[0];
+        assert!(data.kind == QuotaKind::Data && data.period == QuotaPeriod::Daily);
+        assert_eq!(data.unit, "GB");
+        assert_eq!((data.limit, data.used, data.remaining), (2.0, 0.5, 1.5));
+        assert_eq!(data.resets_at.as_deref(), Some("20 Oct 2026 00:00"));
+        let time = &quotas[1];
+        assert!(time.kind == QuotaKind::Time && time.period == QuotaPeriod::Monthly);
+        assert_eq!(
+            (time.limit, time.used, time.remaining),
+            (6000.0, 1530.0, 4470.0)
+        );
+        assert_eq!(time.format_amount(time.remaining), "4470 min");
+    }
+
+    #[test]
+    fn reads_policy_sections() {
+        let quotas = parse_quotas(include_str!("../fixtures/quota_sections.html"));
+        assert_eq!(quotas.len(), 2);
+        let upload = &quotas[0];
+        assert!(upload.kind == QuotaKind::Upload && upload.period == QuotaPeriod::Weekly);
+        assert_eq!(
+            (upload.limit, upload.used, upload.remaining),
+            (10.0, 6.0, 4.0)
+        );
+        assert_eq!(upload.resets_at.as_deref(), Some("26 Oct 2026"));
+        let download = &quotas[1];
+        assert!(download.period == QuotaPeriod::Total);
+        assert_eq!(download.unit, "MB");
+        assert_eq!(download.remaining, 750.0);
+    }
+
+    #[test]
+    fn keeps_every_legacy_figure_in_its_unit() {
+        let quotas = parse_quotas(include_str!("../fixtures/legacy_status.html"));
+        assert_eq!(quotas.len(), 1);
+        let quota = &quotas[0];
+        let legacy = quota.legacy.as_ref().unwrap();
+        assert_eq!(legacy.values, [2.0, 1.5, 300.0, 1024.0, 1.0]);
+        assert_eq!(
+            legacy.units,
+            ["GB", "GB", "MB", "MB", "GB"].map(String::from)
+        );
+        // Usage is compared in the unit of the remaining amount
+        assert_eq!(quota.unit, "GB");
+        assert_eq!((quota.limit, quota.used, quota.remaining), (2.0, 1.0, 1.0));
+        assert_eq!(quota.usage(), 0.5);
+    }
+
+    #[test]
+    fn picks_the_quota_closest_to_running_out() {
+        let quotas = parse_quotas(include_str!("../fixtures/quota_rows.html"));
+        let constrained = most_constrained(&quotas).unwrap();
+        assert!(constrained.kind == QuotaKind::Time);
+        assert_eq!(constrained.format_amount(90.0), "90 min");
+        assert_eq!(quotas[0].format_amount(1536.0), "1.5 TB");
+    }
+
+    #[test]
+    fn rejects_amounts_that_are_not_numbers() {
+        assert_eq!(finite_number(" 1.5 "), Some(1.5));
+        assert_eq!(finite_number("NaN"), None);
+        assert_eq!(finite_number("inf"), None);
+        assert_eq!(finite_number("-"), None);
+        let broken = parse_quotas(&include_str!("../fixtures/legacy_status.html").replacen(
+            ">2&nbsp;",
+            ">NaN&nbsp;",
+            1,
+        ));
+        assert!(broken.is_empty());
+    }
+
+    #[test]
+    fn comparing_quotas_never_panics() {
+        let quotas = parse_quotas(include_str!("../fixtures/quota_rows.html"));
+        let broken = Quota {
+            used: f32::NAN,
+            ..quotas[0].clone()
+        };
+        assert!(most_constrained(&[broken, quotas[1].clone()]).is_some());
+    }
+
+    #[test]
+    fn reads_reset_times() {
+        let local = |text: &str| {
+            let naive = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+            chrono::TimeZone::from_local_datetime(&chrono::Local, &naive).earliest()
+        };
+        let rows = parse_quotas(include_str!("../fixtures/quota_rows.html"));
+        assert_eq!(rows[0].reset_time(), local("2026-10-20 00:00"));
+        let sections = parse_quotas(include_str!("../fixtures/quota_sections.html"));
+        assert_eq!(sections[0].reset_time(), local("2026-10-26 00:00"));
+        let zoned = Quota {
+            resets_at: Some("2026-10-20T00:00:00+05:30".to_string()),
+            ..rows[0].clone()
+        };
+        assert_eq!(
+            zoned.reset_time().map(|at| at.timestamp()),
+            Some(1_792_434_600)
+        );
+        let unreadable = Quota {
+            resets_at: Some("tomorrow".to_string()),
+            ..rows[0].clone()
+        };
+        assert_eq!(unreadable.reset_time(), None);
+    }
+}
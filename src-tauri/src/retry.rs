@@ -0,0 +1,180 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// How the login loop reschedules itself after something goes wrong.
+#[derive(Serialize, Deserialize, Clone, TS)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after a failed login before giving up until the next reconnect
+    pub max_retries: u32,
+    #[ts(type = "number")]
+    pub initial_delay_secs: u64,
+    #[ts(type = "number")]
+    pub max_delay_secs: u64,
+    pub multiplier: f64,
+    /// Fraction of the delay that is randomly added or removed
+    pub jitter: f64,
+    /// Retries after the portal rejects the password. Kept separate since
+    /// repeatedly posting a wrong password can get the account locked.
+    pub invalid_credentials_retries: u32,
+    #[ts(type = "number")]
+    pub invalid_credentials_delay_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            initial_delay_secs: 5,
+            max_delay_secs: 300,
+            multiplier: 2.0,
+            jitter: 0.2,
+            invalid_credentials_retries: 0,
+            invalid_credentials_delay_secs: 900,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoginFailure {
+    InvalidCredentials,
+    /// Never retried by the policy, logging in again waits for the quota to
+    /// reset
+    DataExceeded,
+    Other,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (starting at 0), or `None` once the
+    /// retries for this kind of failure are used up.
+    pub fn next_delay(&self, failure: LoginFailure, attempt: u32) -> Option<chrono::Duration> {
+        if failure == LoginFailure::DataExceeded {
+            None
+        } else if failure == LoginFailure::InvalidCredentials {
+            if attempt < self.invalid_credentials_retries {
+                Some(self.with_jitter(self.invalid_credentials_delay_secs as f64))
+            } else {
+                None
+            }
+        } else if attempt < self.max_retries {
+            Some(self.backoff(attempt))
+        } else {
+            None
+        }
+    }
+
+    /// Exponential delay for the `attempt`th consecutive failure, capped at
+    /// `max_delay_secs`. Never gives up, used while the portal is unreachable.
+    pub fn backoff(&self, attempt: u32) -> chrono::Duration {
+        // Any cap is reached long before the exponent gets large, keeping it
+        // small avoids it wrapping around to a negative
+        let exponent = attempt.min(64) as i32;
+        let delay = (self.initial_delay_secs as f64 * self.multiplier.powi(exponent))
+            .min(self.max_delay_secs as f64);
+        self.with_jitter(delay)
+    }
+
+    fn with_jitter(&self, delay_secs: f64) -> chrono::Duration {
+        let spread = delay_secs * self.jitter.clamp(0.0, 1.0);
+        let jittered = if spread > 0.0 {
+            delay_secs + rand::thread_rng().gen_range(-spread..=spread)
+        } else {
+            delay_secs
+        };
+        chrono::Duration::milliseconds((jittered.max(1.0) * 1000.0) as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn without_jitter() -> RetryPolicy {
+        RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = without_jitter();
+        let delays: Vec<i64> = (0..10)
+            .map(|attempt| policy.backoff(attempt).num_seconds())
+            .collect();
+        assert_eq!(delays, [5, 10, 20, 40, 80, 160, 300, 300, 300, 300]);
+    }
+
+    #[test]
+    fn backoff_never_overflows() {
+        assert_eq!(without_jitter().backoff(u32::MAX).num_seconds(), 300);
+    }
+
+    #[test]
+    fn jitter_stays_within_its_fraction() {
+        let policy = RetryPolicy::default();
+        for _ in 0..1000 {
+            let delay = policy.backoff(3).num_milliseconds();
+            assert!((32_000..=48_000).contains(&delay), "{}", delay);
+        }
+    }
+
+    #[test]
+    fn jitter_is_clamped_and_delay_stays_positive() {
+        let policy = RetryPolicy {
+            initial_delay_secs: 0,
+            jitter: 5.0,
+            ..RetryPolicy::default()
+        };
+        for _ in 0..1000 {
+            assert!(policy.backoff(0).num_milliseconds() >= 1000);
+        }
+    }
+
+    #[test]
+    fn other_failures_give_up_after_max_retries() {
+        let policy = without_jitter();
+        assert_eq!(
+            policy
+                .next_delay(LoginFailure::Other, 0)
+                .map(|delay| delay.num_seconds()),
+            Some(5)
+        );
+        assert!(policy.next_delay(LoginFailure::Other, 4).is_some());
+        assert!(policy.next_delay(LoginFailure::Other, 5).is_none());
+    }
+
+    #[test]
+    fn exceeded_data_is_not_retried() {
+        assert!(without_jitter()
+            .next_delay(LoginFailure::DataExceeded, 0)
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_credentials_are_not_retried_by_default() {
+        assert!(without_jitter()
+            .next_delay(LoginFailure::InvalidCredentials, 0)
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_credentials_use_their_own_delay() {
+        let policy = RetryPolicy {
+            invalid_credentials_retries: 2,
+            ..without_jitter()
+        };
+        for attempt in 0..2 {
+            assert_eq!(
+                policy
+                    .next_delay(LoginFailure::InvalidCredentials, attempt)
+                    .map(|delay| delay.num_seconds()),
+                Some(900)
+            );
+        }
+        assert!(policy
+            .next_delay(LoginFailure::InvalidCredentials, 2)
+            .is_none());
+    }
+}
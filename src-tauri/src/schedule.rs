@@ -0,0 +1,215 @@
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Day {
+    fn of(date: chrono::NaiveDate) -> Day {
+        match date.weekday() {
+            chrono::Weekday::Mon => Day::Mon,
+            chrono::Weekday::Tue => Day::Tue,
+            chrono::Weekday::Wed => Day::Wed,
+            chrono::Weekday::Thu => Day::Thu,
+            chrono::Weekday::Fri => Day::Fri,
+            chrono::Weekday::Sat => Day::Sat,
+            chrono::Weekday::Sun => Day::Sun,
+        }
+    }
+}
+
+/// Hours during which the app may hold a session, as `HH:MM` times. A
+/// window ending before it starts runs past midnight into the next day.
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+pub struct LoginWindow {
+    /// Days the window starts on, every day when empty
+    #[serde(default)]
+    pub days: Vec<Day>,
+    pub start: String,
+    pub end: String,
+}
+
+impl LoginWindow {
+    fn times(&self) -> Option<(NaiveTime, NaiveTime)> {
+        Some((
+            NaiveTime::parse_from_str(&self.start, "%H:%M").ok()?,
+            NaiveTime::parse_from_str(&self.end, "%H:%M").ok()?,
+        ))
+    }
+
+    fn on(&self, date: chrono::NaiveDate) -> bool {
+        self.days.is_empty() || self.days.contains(&Day::of(date))
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let (start, end) = match self.times() {
+            Some(times) => times,
+            None => return false,
+        };
+        let (today, time) = (now.date(), now.time());
+        if start < end {
+            self.on(today) && start <= time && time < end
+        } else {
+            // Overnight, or all day when start and end are the same
+            (self.on(today) && time >= start)
+                || (today.pred_opt().map(|yesterday| self.on(yesterday)) == Some(true)
+                    && time < end)
+        }
+    }
+}
+
+/// When the app is allowed to be logged in. Outside the windows it logs out
+/// and stays idle until the next one opens.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, TS)]
+#[serde(default)]
+pub struct Schedule {
+    pub enabled: bool,
+    pub windows: Vec<LoginWindow>,
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), String> {
+        for window in &self.windows {
+            if window.times().is_none() {
+                return Err(format!(
+                    "Login window {}-{} needs times written as HH:MM",
+                    window.start, window.end
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a session may be held at `now`. An enabled schedule without
+    /// windows never allows one.
+    pub fn allows(&self, now: NaiveDateTime) -> bool {
+        !self.enabled || self.windows.iter().any(|window| window.contains(now))
+    }
+
+    /// The next time `allows` changes its answer, looking a week ahead
+    pub fn next_transition(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if !self.enabled {
+            return None;
+        }
+        let allowed = self.allows(now);
+        let mut candidates: Vec<NaiveDateTime> = Vec::new();
+        for offset in -1..=7 {
+            let date = now.date() + Duration::days(offset);
+            for window in &self.windows {
+                if let Some((start, end)) = window.times() {
+                    candidates.push(date.and_time(start));
+                    candidates.push(date.and_time(end));
+                }
+            }
+        }
+        candidates.sort();
+        candidates
+            .into_iter()
+            .find(|candidate| *candidate > now && self.allows(*candidate) != allowed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn schedule(days: Vec<Day>, start: &str, end: &str) -> Schedule {
+        Schedule {
+            enabled: true,
+            windows: vec![LoginWindow {
+                days,
+                start: start.to_string(),
+                end: end.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn disabled_schedule_always_allows() {
+        let schedule = Schedule::default();
+        assert!(schedule.allows(at("2026-10-19 03:00")));
+        assert_eq!(schedule.next_transition(at("2026-10-19 03:00")), None);
+    }
+
+    #[test]
+    fn enabled_schedule_without_windows_never_allows() {
+        let schedule = Schedule {
+            enabled: true,
+            windows: Vec::new(),
+        };
+        assert!(!schedule.allows(at("2026-10-19 12:00")));
+        assert_eq!(schedule.next_transition(at("2026-10-19 12:00")), None);
+    }
+
+    #[test]
+    fn daytime_window() {
+        let schedule = schedule(vec![], "08:00", "18:00");
+        assert!(!schedule.allows(at("2026-10-19 07:59")));
+        assert!(schedule.allows(at("2026-10-19 08:00")));
+        assert!(!schedule.allows(at("2026-10-19 18:00")));
+        assert_eq!(
+            schedule.next_transition(at("2026-10-19 12:00")),
+            Some(at("2026-10-19 18:00"))
+        );
+        assert_eq!(
+            schedule.next_transition(at("2026-10-19 20:00")),
+            Some(at("2026-10-20 08:00"))
+        );
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_the_day_it_starts() {
+        // 2026-10-23 is a Friday
+        let schedule = schedule(vec![Day::Fri], "22:00", "06:00");
+        assert!(!schedule.allows(at("2026-10-23 21:00")));
+        assert!(schedule.allows(at("2026-10-23 23:00")));
+        assert!(schedule.allows(at("2026-10-24 05:00")));
+        assert!(!schedule.allows(at("2026-10-24 23:00")));
+        // Friday morning is the tail of Thursday night, which is not listed
+        assert!(!schedule.allows(at("2026-10-23 05:00")));
+        assert_eq!(
+            schedule.next_transition(at("2026-10-23 23:00")),
+            Some(at("2026-10-24 06:00"))
+        );
+        assert_eq!(
+            schedule.next_transition(at("2026-10-24 07:00")),
+            Some(at("2026-10-30 22:00"))
+        );
+    }
+
+    #[test]
+    fn same_start_and_end_is_all_day() {
+        let schedule = schedule(vec![Day::Mon], "00:00", "00:00");
+        // 2026-10-19 is a Monday
+        assert!(!schedule.allows(at("2026-10-18 23:59")));
+        assert!(schedule.allows(at("2026-10-19 00:00")));
+        assert!(schedule.allows(at("2026-10-19 23:59")));
+        assert!(!schedule.allows(at("2026-10-20 00:00")));
+        assert_eq!(
+            schedule.next_transition(at("2026-10-19 12:00")),
+            Some(at("2026-10-20 00:00"))
+        );
+    }
+
+    #[test]
+    fn unreadable_times_fail_validation() {
+        assert!(schedule(vec![], "08:00", "18:00").validate().is_ok());
+        let broken = schedule(vec![], "8am", "18:00");
+        assert!(broken.validate().is_err());
+        assert!(!broken.allows(at("2026-10-19 12:00")));
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// One login of this account as listed on the user portal
+#[derive(Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export)]
+pub struct ActiveSession {
+    /// What the portal expects back to log this session out
+    pub id: String,
+    pub ip: Option<String>,
+    pub mac: Option<String>,
+    pub start_time: Option<String>,
+    pub data_used: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Column {
+    Ip,
+    Mac,
+    StartTime,
+    DataUsed,
+    Other,
+}
+
+/// Headers are matched whole, so that a "Description" column is not taken
+/// for the IP address
+fn column(label: &str) -> Column {
+    match label.trim_end_matches(':').to_lowercase().as_str() {
+        "ip" | "ip address" | "client ip" => Column::Ip,
+        "mac" | "mac address" => Column::Mac,
+        "start time" | "login time" | "session start" => Column::StartTime,
+        "data used" | "data usage" | "usage" | "data transfer" | "data transferred" => {
+            Column::DataUsed
+        }
+        _ => Column::Other,
+    }
+}
+
+fn text(node: &tl::Node, parser: &tl::Parser) -> String {
+    node.inner_text(parser)
+        .replace("&nbsp;", " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn is_tag(node: &tl::Node, name: &[u8]) -> bool {
+    node.as_tag()
+        .map(|tag| tag.name().as_bytes().eq_ignore_ascii_case(name))
+        .unwrap_or(false)
+}
+
+/// Reads the live sessions table. Columns are matched on their header so
+/// that deployments showing extra or reordered columns still parse. The
+/// session id is taken from the row's checkbox, falling back to the IP
+/// address which the portal also accepts.
+pub fn parse_sessions(page: &str) -> Vec<ActiveSession> {
+    let mut sessions: Vec<ActiveSession> = Vec::new();
+    let dom = tl::parse(page, tl::ParserOptions::default());
+    if dom.is_err() {
+        return sessions;
+    }
+    let dom = dom.unwrap();
+    let parser = dom.parser();
+    let rows = dom.query_selector("tr");
+    if rows.is_none() {
+        return sessions;
+    }
+    let mut columns: Vec<Column> = Vec::new();
+    for row in rows.unwrap() {
+        let row = row.get(parser).and_then(|node| node.as_tag());
+        if row.is_none() {
+            continue;
+        }
+        let row = row.unwrap();
+        let cells: Vec<&tl::Node> = row
+            .children()
+            .top()
+            .iter()
+            .filter_map(|child| child.get(parser))
+            .filter(|child| is_tag(child, b"td") || is_tag(child, b"th"))
+            .collect();
+        if cells.iter().any(|cell| is_tag(cell, b"th")) {
+            columns = cells
+                .iter()
+                .map(|cell| column(&text(cell, parser)))
+                .collect();
+            continue;
+        }
+        if columns.is_empty() {
+            continue;
+        }
+        let mut session = ActiveSession::default();
+        for (cell, column) in cells.iter().zip(columns.iter()) {
+            let value = text(cell, parser);
+            if value.is_empty() {
+                continue;
+            }
+            match column {
+                Column::Ip => session.ip = Some(value),
+                Column::Mac => session.mac = Some(value),
+                Column::StartTime => session.start_time = Some(value),
+                Column::DataUsed => session.data_used = Some(value),
+                Column::Other => {}
+            }
+        }
+        let checkbox_value = row
+            .query_selector(parser, "input[type=checkbox]")
+            .and_then(|mut inputs| inputs.next())
+            .and_then(|input| input.get(parser))
+            .and_then(|input| input.as_tag())
+            .and_then(|input| input.attributes().get("value").flatten())
+            .map(|value| value.as_utf8_str().to_string());
+        if let Some(id) = checkbox_value.or_else(|| session.ip.clone()) {
+            session.id = id;
+            sessions.push(session);
+        }
+    }
+    sessions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_columns_by_exact_header() {
+        let sessions = parse_sessions(include_str!("../fixtures/legacy_sessions.html"));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "8821");
+        assert_eq!(sessions[0].ip.as_deref(), Some("10.20.30.40"));
+        assert_eq!(sessions[0].mac.as_deref(), Some("aa:bb:cc:dd:ee:ff"));
+        assert_eq!(sessions[0].start_time.as_deref(), Some("19 Oct 2026 09:12"));
+        assert_eq!(sessions[0].data_used.as_deref(), Some("1.25 GB"));
+    }
+
+    #[test]
+    fn falls_back_to_ip_without_checkbox() {
+        let sessions = parse_sessions(include_str!("../fixtures/legacy_sessions.html"));
+        assert_eq!(sessions[1].id, "10.20.30.41");
+        assert_eq!(sessions[1].mac, None);
+    }
+
+    #[test]
+    fn description_is_not_an_ip_column() {
+        assert!(matches!(column("Description"), Column::Other));
+        assert!(matches!(column("Client IP"), Column::Ip));
+        assert!(matches!(column("Data Used:"), Column::DataUsed));
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use tauri::api::file;
+use ts_rs::TS;
+
+use crate::captive::CampusProfile;
+use crate::error::AppResult;
+use crate::hooks::HookSettings;
+use crate::http::HttpSettings;
+use crate::mqtt::MqttSettings;
+use crate::retry::RetryPolicy;
+use crate::schedule::Schedule;
+use crate::secret::Secret;
+use crate::status_file::StatusFileSettings;
+use crate::tls::TlsSettings;
+use crate::tray_icon::TrayIconSettings;
+use crate::trust::TrustPolicy;
+use crate::webhooks::WebhookTarget;
+
+#[derive(Serialize, Deserialize, Clone, Default, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct Settings {
+    pub campus: CampusProfile,
+    pub trust: TrustPolicy,
+    pub retry: RetryPolicy,
+    pub http: HttpSettings,
+    pub tls: TlsSettings,
+    pub tray_icon: TrayIconSettings,
+    pub schedule: Schedule,
+    pub hooks: HookSettings,
+    pub webhooks: Vec<WebhookTarget>,
+    pub mqtt: MqttSettings,
+    pub status_file: StatusFileSettings,
+}
+
+impl Settings {
+    /// A copy for the window with every secret left out
+    pub fn redacted(&self) -> Settings {
+        let mut settings = self.clone();
+        settings.mqtt.password = None;
+        for target in settings.webhooks.iter_mut() {
+            target.secret = None;
+        }
+        settings
+    }
+
+    /// Fills in the secrets the window left out from `saved`, the way a
+    /// missing password keeps the saved one. An empty secret removes it.
+    pub fn keep_secrets(&mut self, saved: &Settings) {
+        self.mqtt.password = keep(self.mqtt.password.take(), &saved.mqtt.password);
+        for target in self.webhooks.iter_mut() {
+            let saved_secret = saved
+                .webhooks
+                .iter()
+                .find(|saved| !target.id.is_empty() && saved.id == target.id)
+                .and_then(|saved| saved.secret.clone());
+            target.secret = keep(target.secret.take(), &saved_secret);
+        }
+    }
+}
+
+fn keep(entered: Option<Secret>, saved: &Option<Secret>) -> Option<Secret> {
+    match entered {
+        Some(secret) if secret.is_empty() => None,
+        Some(secret) => Some(secret),
+        None => saved.clone(),
+    }
+}
+
+pub fn save_settings(settings: &Settings, save_file: &std::path::Path) -> AppResult<()> {
+    let mut file = std::fs::File::create(save_file)?;
+    write!(&mut file, "{}", serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
+
+/// Missing or unreadable settings fall back to the defaults so that an
+/// older install keeps working exactly as before.
+pub fn load_settings(save_file: &std::path::Path) -> Settings {
+    let settings_string = file::read_string(save_file);
+    if settings_string.is_ok() {
+        serde_json::from_str(&settings_string.unwrap()).unwrap_or_default()
+    } else {
+        Settings::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved() -> Settings {
+        let mut settings = Settings::default();
+        settings.mqtt.password = Some(Secret::new("broker".to_string()));
+        settings.webhooks = vec![WebhookTarget {
+            id: "0123456789abcdef".to_string(),
+            url: "https://hooks.example.net/campnet".to_string(),
+            secret: Some(Secret::new("hunter2".to_string())),
+            events: Vec::new(),
+        }];
+        settings
+    }
+
+    #[test]
+    fn window_never_sees_secrets() {
+        let redacted = saved().redacted();
+        assert_eq!(redacted.mqtt.password, None);
+        assert_eq!(redacted.webhooks[0].secret, None);
+        assert_eq!(
+            redacted.webhooks[0].url,
+            "https://hooks.example.net/campnet"
+        );
+    }
+
+    #[test]
+    fn left_out_secrets_are_kept() {
+        let saved = saved();
+        let mut entered = saved.redacted();
+        entered.webhooks[0].url = "https://hooks.example.net/moved".to_string();
+        entered.keep_secrets(&saved);
+        assert_eq!(entered.mqtt.password, saved.mqtt.password);
+        assert_eq!(entered.webhooks[0].secret, saved.webhooks[0].secret);
+    }
+
+    #[test]
+    fn entered_or_emptied_secrets_replace_saved_ones() {
+        let saved = saved();
+        let mut entered = saved.redacted();
+        entered.mqtt.password = Some(Secret::new("".to_string()));
+        entered.webhooks[0].secret = Some(Secret::new("new".to_string()));
+        entered.keep_secrets(&saved);
+        assert_eq!(entered.mqtt.password, None);
+        assert_eq!(entered.webhooks[0].secret.as_ref().unwrap().expose(), "new");
+    }
+
+    #[test]
+    fn new_targets_get_no_saved_secret() {
+        let saved = saved();
+        let mut entered = saved.redacted();
+        entered.webhooks[0].id = "".to_string();
+        entered.keep_secrets(&saved);
+        assert_eq!(entered.webhooks[0].secret, None);
+    }
+}
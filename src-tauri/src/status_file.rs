@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use ts_rs::TS;
+
+/// Time allowed on top of the longest wait between writes, for the round
+/// itself to run into timeouts
+const MARGIN_SECS: i64 = 60;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(default)]
+pub struct StatusFileSettings {
+    pub enabled: bool,
+    /// One line summary written to `status.txt`, with `{state}`, `{status}`,
+    /// `{used}`, `{remaining}`, `{limit}`, `{unit}`, `{remaining_text}` and
+    /// `{percentage}` filled in. Only the JSON file is written without it.
+    pub template: Option<String>,
+}
+
+impl Default for StatusFileSettings {
+    fn default() -> Self {
+        StatusFileSettings {
+            enabled: true,
+            template: None,
+        }
+    }
+}
+
+/// What `status.json` holds. Usage fields are missing until the first poll.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Status {
+    pub state: String,
+    /// Connection state as shown in the tray menu
+    pub status: String,
+    pub used: Option<f32>,
+    pub remaining: Option<f32>,
+    pub limit: Option<f32>,
+    pub unit: Option<String>,
+    /// Remaining amount in a readable unit, like `3.2 GB`
+    pub remaining_text: Option<String>,
+    /// Share of the quota used up
+    pub percentage: Option<u8>,
+    /// Unix milliseconds of the next login attempt, if one is scheduled
+    pub next_retry: Option<i64>,
+    /// Unix milliseconds of when this was written
+    pub updated: i64,
+    /// Unix milliseconds after which the app should have written again, a
+    /// status older than that means it is no longer running
+    #[serde(default)]
+    pub stale_after: i64,
+    /// The line from the template, or a default one
+    pub text: String,
+}
+
+impl Status {
+    pub fn render(&self, template: &str) -> String {
+        let number = |value: Option<f32>| value.map(|value| format!("{:.1}", value));
+        [
+            ("state", Some(self.state.to_string())),
+            ("status", Some(self.status.to_string())),
+            ("used", number(self.used)),
+            ("remaining", number(self.remaining)),
+            ("limit", number(self.limit)),
+            ("unit", self.unit.clone()),
+            ("remaining_text", self.remaining_text.clone()),
+            ("percentage", self.percentage.map(|value| value.to_string())),
+        ]
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(
+                &format!("{{{}}}", name),
+                value.as_deref().unwrap_or_default(),
+            )
+        })
+    }
+
+    fn default_text(&self) -> String {
+        match &self.remaining_text {
+            Some(remaining) => format!("{} ({} left)", self.status, remaining),
+            None => self.status.to_string(),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/autocampnet`, or the temporary directory on systems
+/// without one
+pub fn dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("autocampnet")
+}
+
+/// When a status written at `updated` goes out of date, given the longest
+/// the app may wait before writing again and the jitter added to that wait
+pub fn stale_after(updated: i64, longest_wait_secs: u64, jitter: f64) -> i64 {
+    let wait = longest_wait_secs as f64 * (1.0 + jitter.clamp(0.0, 1.0));
+    updated + (wait.ceil() as i64 + MARGIN_SECS) * 1000
+}
+
+/// Writes next to the file and renames it over, so readers never see half
+/// a file. Each file gets a temporary name of its own, as in
+/// `status.json.tmp`.
+fn write_atomic(path: PathBuf, contents: &str) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_file = path.with_file_name(temp_name);
+    let mut file = std::fs::File::create(&temp_file)?;
+    file.write_all(contents.as_bytes())?;
+    std::fs::rename(&temp_file, path)
+}
+
+/// Fills in `text` and writes `status.json`, and `status.txt` when there is
+/// a template
+pub fn write(settings: &StatusFileSettings, status: &mut Status) -> std::io::Result<()> {
+    let dir = dir();
+    std::fs::create_dir_all(&dir)?;
+    status.text = match &settings.template {
+        Some(template) => status.render(template),
+        None => status.default_text(),
+    };
+    write_atomic(dir.join("status.json"), &serde_json::to_string(status)?)?;
+    if settings.template.is_some() {
+        write_atomic(dir.join("status.txt"), &format!("{}\n", status.text))?;
+    }
+    Ok(())
+}
+
+fn read() -> Option<Status> {
+    let status = std::fs::read_to_string(dir().join("status.json")).ok()?;
+    serde_json::from_str(&status).ok()
+}
+
+/// The status as one line of JSON for a waybar custom module with
+/// `"return-type": "json"`. The state is used as the class for styling.
+pub fn waybar() -> String {
+    waybar_output(read(), chrono::Local::now().timestamp_millis())
+}
+
+fn waybar_output(status: Option<Status>, now: i64) -> String {
+    let status = status.filter(|status| now < status.stale_after);
+    let output = match status {
+        Some(status) => {
+            let mut tooltip = vec![status.status.to_string()];
+            if let (Some(remaining), Some(percentage)) = (&status.remaining_text, status.percentage)
+            {
+                tooltip.push(format!("Remaining: {} ({}% used)", remaining, percentage));
+            }
+            serde_json::json!({
+                "text": status.text,
+                "alt": status.state,
+                "tooltip": tooltip.join("\n"),
+                "class": status.state,
+                "percentage": status.percentage.unwrap_or(0),
+            })
+        }
+        None => serde_json::json!({
+            "text": "Auto Campnet is not running",
+            "alt": "stopped",
+            "tooltip": "No recent status",
+            "class": "stopped",
+            "percentage": 0,
+        }),
+    };
+    output.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(updated: i64, stale_after: i64) -> Status {
+        Status {
+            state: "connected".to_string(),
+            status: "Connected".to_string(),
+            used: Some(0.5),
+            remaining: Some(1.5),
+            limit: Some(2.0),
+            unit: Some("GB".to_string()),
+            remaining_text: Some("1.5 GB".to_string()),
+            percentage: Some(25),
+            next_retry: None,
+            updated,
+            stale_after,
+            text: "Connected (1.5 GB left)".to_string(),
+        }
+    }
+
+    #[test]
+    fn outlasts_the_longest_retry_delay() {
+        // The default retry policy waits up to 300 seconds, with 20% jitter
+        let stale_after = stale_after(0, 300, 0.2);
+        assert_eq!(stale_after, (360 + MARGIN_SECS) * 1000);
+        let status = status(0, stale_after);
+        let output: serde_json::Value =
+            serde_json::from_str(&waybar_output(Some(status.clone()), 359_000)).unwrap();
+        assert_eq!(output["class"], "connected");
+        assert_eq!(output["percentage"], 25);
+        let output: serde_json::Value =
+            serde_json::from_str(&waybar_output(Some(status), stale_after)).unwrap();
+        assert_eq!(output["class"], "stopped");
+    }
+
+    #[test]
+    fn status_files_do_not_share_a_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("status-{:016x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_atomic(dir.join("status.json"), "{}").unwrap();
+        write_atomic(dir.join("status.txt"), "line\n").unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["status.json", "status.txt"]);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn renders_the_template() {
+        let status = status(0, 0);
+        assert_eq!(
+            status.render("{status}: {remaining_text} ({percentage}%) {missing}"),
+            "Connected: 1.5 GB (25%) {missing}"
+        );
+    }
+}
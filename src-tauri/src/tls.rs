@@ -0,0 +1,276 @@
+use base64::Engine;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, Error, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+use ts_rs::TS;
+
+#[derive(Serialize, Deserialize, Clone, Default, TS)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// PEM file with certificate authorities to trust on top of the system ones
+    pub ca_bundle: Option<String>,
+    /// Base64 SHA-256 of the login endpoint's public key, as in
+    /// `sha256//<base64>` (the prefix is optional)
+    pub login_pin: Option<String>,
+    pub portal_pin: Option<String>,
+}
+
+/// Why the last handshake was refused, kept so that a failed request can be
+/// reported as a certificate problem rather than the portal being down.
+#[derive(Serialize, Clone, TS)]
+pub struct TlsFailure {
+    pub endpoint: String,
+    pub reason: String,
+    /// Pin of the certificate that was actually served, to help set up pinning
+    pub served_pin: Option<String>,
+}
+
+pub type TlsFailureSlot = Arc<Mutex<Option<TlsFailure>>>;
+
+/// Checks the certificate chain against the system and extra roots, unless a
+/// pin is configured. A matching pin is trusted on its own so that an expired
+/// or self-signed appliance certificate can still be used, while any other
+/// key is rejected even if it chains to a trusted root. A fingerprint from
+/// the trust policy is required on top of either.
+struct PinningVerifier {
+    endpoint: String,
+    pin: Option<String>,
+    /// Lowercase hex SHA-256 of the whole certificate
+    fingerprint: Option<String>,
+    webpki: WebPkiVerifier,
+    failure: TlsFailureSlot,
+}
+
+impl PinningVerifier {
+    fn fail(&self, reason: String, served_pin: Option<String>) -> Error {
+        *self.failure.lock().unwrap_or_else(PoisonError::into_inner) = Some(TlsFailure {
+            endpoint: self.endpoint.to_string(),
+            reason: reason.clone(),
+            served_pin,
+        });
+        Error::General(reason)
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let served_pin = spki_pin(&end_entity.0);
+        let verified = if let Some(pin) = &self.pin {
+            if served_pin.as_deref() == Some(normalise_pin(pin)) {
+                Ok(ServerCertVerified::assertion())
+            } else {
+                Err(self.fail(
+                    "certificate does not match the configured pin".to_string(),
+                    served_pin.clone(),
+                ))
+            }
+        } else {
+            self.webpki
+                .verify_server_cert(
+                    end_entity,
+                    intermediates,
+                    server_name,
+                    scts,
+                    ocsp_response,
+                    now,
+                )
+                .map_err(|error| self.fail(error.to_string(), served_pin.clone()))
+        };
+        let verified = verified.and_then(|verified| match &self.fingerprint {
+            Some(fingerprint) if *fingerprint != certificate_fingerprint(&end_entity.0) => {
+                Err(self.fail(
+                    "certificate does not match the trusted fingerprint".to_string(),
+                    served_pin,
+                ))
+            }
+            _ => Ok(verified),
+        });
+        if verified.is_ok() {
+            *self.failure.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        }
+        verified
+    }
+}
+
+fn normalise_pin(pin: &str) -> &str {
+    pin.trim().trim_start_matches("sha256//")
+}
+
+/// Drops separators such as the colons browsers show fingerprints with
+fn normalise_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn certificate_fingerprint(certificate: &[u8]) -> String {
+    Sha256::digest(certificate)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Base64 SHA-256 of the certificate's SubjectPublicKeyInfo
+pub fn spki_pin(certificate: &[u8]) -> Option<String> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(certificate).ok()?;
+    Some(base64::engine::general_purpose::STANDARD.encode(Sha256::digest(parsed.public_key().raw)))
+}
+
+pub fn root_store(settings: &TlsSettings) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    // A system without a certificate store still gets clients, so that the
+    // app starts and pinned endpoints keep working. Unparseable system
+    // certificates are skipped, same as other TLS stacks do.
+    let native_certs = rustls_native_certs::load_native_certs().unwrap_or_default();
+    roots.add_parsable_certificates(
+        &native_certs
+            .into_iter()
+            .map(|certificate| certificate.0)
+            .collect::<Vec<Vec<u8>>>(),
+    );
+    if let Some(bundle_path) = &settings.ca_bundle {
+        let bundle = std::fs::File::open(bundle_path)
+            .map_err(|e| format!("Could not open CA bundle {}: {}", bundle_path, e))?;
+        let certificates = rustls_pemfile::certs(&mut std::io::BufReader::new(bundle))
+            .map_err(|e| format!("Could not read CA bundle {}: {}", bundle_path, e))?;
+        for certificate in certificates {
+            roots
+                .add(&Certificate(certificate))
+                .map_err(|e| format!("Invalid certificate in {}: {}", bundle_path, e))?;
+        }
+    }
+    Ok(roots)
+}
+
+pub fn client_config(
+    roots: RootCertStore,
+    endpoint: &str,
+    pin: Option<String>,
+    fingerprint: Option<&str>,
+    failure: TlsFailureSlot,
+) -> ClientConfig {
+    let verifier = PinningVerifier {
+        endpoint: endpoint.to_string(),
+        pin,
+        fingerprint: fingerprint.map(normalise_fingerprint),
+        webpki: WebPkiVerifier::new(roots, None),
+        failure,
+    };
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed certificate for `campnet.test`
+    const CERTIFICATE: &[u8] = include_bytes!("../fixtures/campnet.der");
+    const PIN: &str = "K11nAiCdBrj7Ro3E7igwkdQhhkPaRonWD8Bi9Acpfuc=";
+    const FINGERPRINT: &str = "703c8d4f43d37b123d610408a1bb62d43f98acdc3a11cebfc2ea2d57261dc172";
+
+    fn verifier(pin: Option<&str>, fingerprint: Option<&str>) -> PinningVerifier {
+        PinningVerifier {
+            endpoint: "login endpoint".to_string(),
+            pin: pin.map(|pin| pin.to_string()),
+            fingerprint: fingerprint.map(normalise_fingerprint),
+            webpki: WebPkiVerifier::new(RootCertStore::empty(), None),
+            failure: Arc::default(),
+        }
+    }
+
+    fn verify(verifier: &PinningVerifier) -> Result<ServerCertVerified, Error> {
+        verifier.verify_server_cert(
+            &Certificate(CERTIFICATE.to_vec()),
+            &[],
+            &ServerName::try_from("campnet.test").unwrap(),
+            &mut std::iter::empty(),
+            &[],
+            SystemTime::now(),
+        )
+    }
+
+    #[test]
+    fn pin_is_the_hash_of_the_public_key() {
+        assert_eq!(spki_pin(CERTIFICATE).as_deref(), Some(PIN));
+        assert_eq!(spki_pin(b"not a certificate"), None);
+    }
+
+    #[test]
+    fn untrusted_certificate_is_rejected_without_a_pin() {
+        let verifier = verifier(None, None);
+        assert!(verify(&verifier).is_err());
+        let failure = verifier.failure.lock().unwrap().clone().unwrap();
+        assert_eq!(failure.endpoint, "login endpoint");
+        assert_eq!(failure.served_pin.as_deref(), Some(PIN));
+    }
+
+    #[test]
+    fn matching_pin_is_trusted_on_its_own() {
+        for pin in [PIN.to_string(), format!(" sha256//{} ", PIN)] {
+            let verifier = verifier(Some(&pin), None);
+            assert!(verify(&verifier).is_ok());
+            assert!(verifier.failure.lock().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn other_pin_is_rejected() {
+        let verifier = verifier(
+            Some("sha256//AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="),
+            None,
+        );
+        assert!(verify(&verifier).is_err());
+        let failure = verifier.failure.lock().unwrap().clone().unwrap();
+        assert_eq!(
+            failure.reason,
+            "certificate does not match the configured pin"
+        );
+        assert_eq!(failure.served_pin.as_deref(), Some(PIN));
+    }
+
+    #[test]
+    fn success_clears_an_earlier_failure() {
+        let verifier = verifier(Some(PIN), None);
+        *verifier.failure.lock().unwrap() = Some(TlsFailure {
+            endpoint: "login endpoint".to_string(),
+            reason: "earlier".to_string(),
+            served_pin: None,
+        });
+        assert!(verify(&verifier).is_ok());
+        assert!(verifier.failure.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn fingerprint_is_required_on_top_of_the_pin() {
+        let colons = FINGERPRINT
+            .to_uppercase()
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| std::str::from_utf8(pair).unwrap())
+            .collect::<Vec<&str>>()
+            .join(":");
+        assert!(verify(&verifier(Some(PIN), Some(&colons))).is_ok());
+        let verifier = verifier(Some(PIN), Some(&"00".repeat(32)));
+        assert!(verify(&verifier).is_err());
+        assert_eq!(
+            verifier.failure.lock().unwrap().clone().unwrap().reason,
+            "certificate does not match the trusted fingerprint"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use ts_rs::TS;
+
+use crate::ConnectionState;
+
+/// Width and height of the rendered icon. Trays scale it down further, so
+/// rendering bigger only costs time.
+pub const SIZE: u32 = 64;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(default)]
+pub struct TrayIconSettings {
+    /// Draw the icon at runtime instead of using the bundled ones
+    pub dynamic: bool,
+    /// Print the remaining percentage over the icon
+    pub show_percentage: bool,
+}
+
+impl Default for TrayIconSettings {
+    fn default() -> Self {
+        TrayIconSettings {
+            dynamic: true,
+            show_percentage: false,
+        }
+    }
+}
+
+/// Everything the icon shows. A new icon is only made when this changes.
+#[derive(Clone, PartialEq)]
+pub struct IconKey {
+    pub state: ConnectionState,
+    /// Percentage of the most constrained quota used up, if known
+    pub used: Option<u8>,
+    pub settings: TrayIconSettings,
+}
+
+struct Image {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Renders the tray icon, keeping the scaled down base image and the key
+/// of the icon currently shown around between updates.
+pub struct TrayIcon {
+    base_path: Option<PathBuf>,
+    base: Mutex<Option<Vec<u8>>>,
+    shown: Mutex<Option<IconKey>>,
+}
+
+impl TrayIcon {
+    pub fn new(base_path: Option<PathBuf>) -> Self {
+        TrayIcon {
+            base_path,
+            base: Mutex::new(None),
+            shown: Mutex::new(None),
+        }
+    }
+
+    /// Records `key` as shown, returning false when it already was
+    pub fn changed(&self, key: &IconKey) -> bool {
+        let mut shown = self.shown.lock().unwrap_or_else(PoisonError::into_inner);
+        if shown.as_ref() == Some(key) {
+            false
+        } else {
+            *shown = Some(key.clone());
+            true
+        }
+    }
+
+    /// Draws a ring around `base.png`, coloured by the connection state and
+    /// filled by the share of the quota used up.
+    pub fn render(&self, key: &IconKey) -> Result<Vec<u8>, String> {
+        let mut base = self.base.lock().unwrap_or_else(PoisonError::into_inner);
+        if base.is_none() {
+            let path = self
+                .base_path
+                .as_ref()
+                .ok_or("Base icon is missing".to_string())?;
+            *base = Some(scale(&decode(path)?, SIZE));
+        }
+        let mut rgba = base.as_ref().unwrap().clone();
+        let fraction = key.used.map(|used| used as f32 / 100.0).unwrap_or(1.0);
+        draw_ring(&mut rgba, fraction, state_colour(key.state));
+        if let (true, Some(used)) = (key.settings.show_percentage, key.used) {
+            draw_number(&mut rgba, 100 - used.min(100) as u32);
+        }
+        Ok(rgba)
+    }
+}
+
+fn state_colour(state: ConnectionState) -> [u8; 3] {
+    match state {
+        ConnectionState::Connected => [46, 204, 113],
+        ConnectionState::Idle => [149, 165, 166],
+        ConnectionState::Disconnected => [231, 76, 60],
+        ConnectionState::CertificateError => [230, 126, 34],
+        ConnectionState::OutsideSchedule => [52, 152, 219],
+    }
+}
+
+fn decode(path: &PathBuf) -> Result<Image, String> {
+    let file = std::fs::File::open(path).map_err(|err| err.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    let pixels = &buffer[..info.buffer_size()];
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels
+            .iter()
+            .flat_map(|&pixel| [pixel, pixel, pixel, 255])
+            .collect(),
+        png::ColorType::Indexed => return Err("Unexpected indexed base icon".to_string()),
+    };
+    Ok(Image {
+        rgba,
+        width: info.width,
+        height: info.height,
+    })
+}
+
+/// Box filter down to `size` square, averaging with premultiplied alpha so
+/// that transparent pixels do not darken the edges
+fn scale(image: &Image, size: u32) -> Vec<u8> {
+    let mut scaled = vec![0; (size * size * 4) as usize];
+    for y in 0..size {
+        for x in 0..size {
+            let (x0, x1) = (x * image.width / size, (x + 1) * image.width / size);
+            let (y0, y1) = (y * image.height / size, (y + 1) * image.height / size);
+            let mut sum = [0.0f32; 4];
+            let mut count = 0.0;
+            for source_y in y0..y1.max(y0 + 1) {
+                for source_x in x0..x1.max(x0 + 1) {
+                    let index = ((source_y * image.width + source_x) * 4) as usize;
+                    let alpha = image.rgba[index + 3] as f32 / 255.0;
+                    for (total, &value) in sum.iter_mut().zip(&image.rgba[index..index + 3]) {
+                        *total += value as f32 * alpha;
+                    }
+                    sum[3] += alpha;
+                    count += 1.0;
+                }
+            }
+            let index = ((y * size + x) * 4) as usize;
+            if sum[3] > 0.0 {
+                for channel in 0..3 {
+                    scaled[index + channel] = (sum[channel] / sum[3]).round() as u8;
+                }
+            }
+            scaled[index + 3] = (sum[3] / count * 255.0).round() as u8;
+        }
+    }
+    scaled
+}
+
+/// Paints `colour` over the pixel at `index` with `coverage` opacity
+fn blend(rgba: &mut [u8], index: usize, colour: [u8; 3], coverage: f32) {
+    let coverage = coverage.clamp(0.0, 1.0);
+    if coverage == 0.0 {
+        return;
+    }
+    let alpha = rgba[index + 3] as f32 / 255.0;
+    let out_alpha = coverage + alpha * (1.0 - coverage);
+    for channel in 0..3 {
+        rgba[index + channel] = ((colour[channel] as f32 * coverage
+            + rgba[index + channel] as f32 * alpha * (1.0 - coverage))
+            / out_alpha)
+            .round() as u8;
+    }
+    rgba[index + 3] = (out_alpha * 255.0).round() as u8;
+}
+
+/// Ring along the edge of the icon, filled clockwise from the top
+fn draw_ring(rgba: &mut [u8], fraction: f32, colour: [u8; 3]) {
+    let centre = SIZE as f32 / 2.0;
+    let outer = centre;
+    let inner = centre - SIZE as f32 / 10.0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let dx = x as f32 + 0.5 - centre;
+            let dy = y as f32 + 0.5 - centre;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let coverage =
+                (outer - distance + 0.5).clamp(0.0, 1.0) * (distance - inner + 0.5).clamp(0.0, 1.0);
+            let mut angle = dx.atan2(-dy) / std::f32::consts::TAU;
+            if angle < 0.0 {
+                angle += 1.0;
+            }
+            let index = ((y * SIZE + x) * 4) as usize;
+            if angle < fraction {
+                blend(rgba, index, colour, coverage);
+            } else {
+                blend(rgba, index, [40, 40, 40], coverage * 0.6);
+            }
+        }
+    }
+}
+
+/// Digits of a 3x5 pixel font, one row per byte with the leftmost pixel in
+/// the highest of the three bits
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Prints `number` in white on a dark box along the bottom of the icon
+fn draw_number(rgba: &mut [u8], number: u32) {
+    let pixel = SIZE / 16;
+    let digits: Vec<usize> = number
+        .to_string()
+        .chars()
+        .map(|digit| digit.to_digit(10).unwrap() as usize)
+        .collect();
+    let width = (digits.len() as u32 * 4 - 1) * pixel;
+    let height = 5 * pixel;
+    let left = (SIZE - width) / 2;
+    let top = SIZE - height - 2 * pixel;
+    for y in top - pixel..top + height + pixel {
+        for x in left - pixel..left + width + pixel {
+            blend(rgba, ((y * SIZE + x) * 4) as usize, [0, 0, 0], 0.75);
+        }
+    }
+    for (position, &digit) in digits.iter().enumerate() {
+        for (row, bits) in DIGITS[digit].iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                let x0 = left + (position as u32 * 4 + column) * pixel;
+                let y0 = top + row as u32 * pixel;
+                for y in y0..y0 + pixel {
+                    for x in x0..x0 + pixel {
+                        blend(rgba, ((y * SIZE + x) * 4) as usize, [255, 255, 255], 1.0);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::str::FromStr;
+use ts_rs::TS;
+
+/// Rules a network has to satisfy before credentials are sent to it. Every
+/// rule is optional, an empty policy trusts every network.
+#[derive(Serialize, Deserialize, Clone, Default, TS)]
+#[serde(default)]
+pub struct TrustPolicy {
+    /// Wi-Fi network names on which logging in is allowed
+    pub allowed_ssids: Vec<String>,
+    /// Default gateway addresses (`10.1.0.1`) or subnets (`10.1.0.0/16`)
+    pub allowed_gateways: Vec<String>,
+    /// Hex encoded SHA-256 of the certificate served by the login endpoint.
+    /// Checked on every handshake with it, so credentials only ever go out
+    /// over a connection to that certificate.
+    pub certificate_fingerprint: Option<String>,
+}
+
+pub enum Untrusted {
+    Ssid(Option<String>),
+    Gateway(Option<IpAddr>),
+}
+
+impl Untrusted {
+    pub fn describe(&self) -> String {
+        match self {
+            Untrusted::Ssid(Some(ssid)) => format!("Wi-Fi network {} is not trusted", ssid),
+            Untrusted::Ssid(None) => "Could not determine the Wi-Fi network".to_string(),
+            Untrusted::Gateway(Some(gateway)) => format!("Gateway {} is not trusted", gateway),
+            Untrusted::Gateway(None) => "Could not determine the default gateway".to_string(),
+        }
+    }
+}
+
+/// Checks the currently connected network against the policy. Anything that
+/// cannot be determined is treated as not matching. The certificate
+/// fingerprint is left to the login client, see `tls::client_config`.
+pub fn check_network(policy: &TrustPolicy) -> Result<(), Untrusted> {
+    if !policy.allowed_ssids.is_empty() {
+        let ssid = current_ssid();
+        if ssid.is_none() || !policy.allowed_ssids.contains(ssid.as_ref().unwrap()) {
+            return Err(Untrusted::Ssid(ssid));
+        }
+    }
+    if !policy.allowed_gateways.is_empty() {
+        let gateway = default_gateway();
+        if gateway.is_none()
+            || !policy
+                .allowed_gateways
+                .iter()
+                .any(|rule| gateway_matches(rule, gateway.unwrap()))
+        {
+            return Err(Untrusted::Gateway(gateway));
+        }
+    }
+    Ok(())
+}
+
+fn gateway_matches(rule: &str, gateway: IpAddr) -> bool {
+    if rule.contains('/') {
+        ipnet::IpNet::from_str(rule.trim())
+            .map(|net| net.contains(&gateway))
+            .unwrap_or(false)
+    } else {
+        IpAddr::from_str(rule.trim())
+            .map(|address| address == gateway)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn current_ssid() -> Option<String> {
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    let connection = Connection::system().ok()?;
+    let network_manager = Proxy::new(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        "/org/freedesktop/NetworkManager",
+        "org.freedesktop.NetworkManager",
+    )
+    .ok()?;
+    let devices: Vec<OwnedObjectPath> = network_manager.get_property("Devices").ok()?;
+    for device_path in devices {
+        let device = Proxy::new(
+            &connection,
+            "org.freedesktop.NetworkManager",
+            device_path.as_str(),
+            "org.freedesktop.NetworkManager.Device",
+        )
+        .ok()?;
+        // NM_DEVICE_TYPE_WIFI
+        if device.get_property::<u32>("DeviceType").ok()? != 2 {
+            continue;
+        }
+        let wireless = Proxy::new(
+            &connection,
+            "org.freedesktop.NetworkManager",
+            device_path.as_str(),
+            "org.freedesktop.NetworkManager.Device.Wireless",
+        )
+        .ok()?;
+        let access_point_path: OwnedObjectPath = wireless.get_property("ActiveAccessPoint").ok()?;
+        if access_point_path.as_str() == "/" {
+            continue;
+        }
+        let access_point = Proxy::new(
+            &connection,
+            "org.freedesktop.NetworkManager",
+            access_point_path.as_str(),
+            "org.freedesktop.NetworkManager.AccessPoint",
+        )
+        .ok()?;
+        let ssid: Vec<u8> = access_point.get_property("Ssid").ok()?;
+        return Some(String::from_utf8_lossy(&ssid).to_string());
+    }
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn current_ssid() -> Option<String> {
+    let output = std::process::Command::new("networksetup")
+        .args(["-getairportnetwork", "en0"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .split("Current Wi-Fi Network:")
+        .nth(1)
+        .map(|ssid| ssid.trim().to_string())
+}
+
+#[cfg(windows)]
+fn current_ssid() -> Option<String> {
+    let output = hidden_command("netsh")
+        .args(["wlan", "show", "interfaces"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("SSID"))
+        .and_then(|line| line.splitn(2, ':').nth(1))
+        .map(|ssid| ssid.trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn default_gateway() -> Option<IpAddr> {
+    // Columns are Iface, Destination, Gateway, ... with addresses printed as
+    // the raw in-memory value, so they need converting back from network order
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    routes.lines().skip(1).find_map(|line| {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() > 2 && columns[1] == "00000000" {
+            u32::from_str_radix(columns[2], 16)
+                .ok()
+                .map(|gateway| IpAddr::V4(std::net::Ipv4Addr::from(u32::from_be(gateway))))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn default_gateway() -> Option<IpAddr> {
+    let output = std::process::Command::new("route")
+        .args(["-n", "get", "default"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("gateway:"))
+        .and_then(|line| IpAddr::from_str(line.trim_start_matches("gateway:").trim()).ok())
+}
+
+#[cfg(windows)]
+fn default_gateway() -> Option<IpAddr> {
+    let output = hidden_command("route")
+        .args(["print", "0.0.0.0"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() > 2 && columns[0] == "0.0.0.0" && columns[1] == "0.0.0.0" {
+                std::net::Ipv4Addr::from_str(columns[2])
+                    .ok()
+                    .map(IpAddr::V4)
+            } else {
+                None
+            }
+        })
+}
+
+#[cfg(windows)]
+fn hidden_command(program: &str) -> std::process::Command {
+    use std::os::windows::process::CommandExt;
+    // CREATE_NO_WINDOW, otherwise a console flashes up on every check
+    let mut command = std::process::Command::new(program);
+    command.creation_flags(0x08000000);
+    command
+}
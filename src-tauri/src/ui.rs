@@ -0,0 +1,43 @@
+use tauri::api::notification::Notification;
+use tauri::Manager;
+
+use crate::error::{AppError, AppResult, OrReport};
+
+/// Shows a notification, without a body when `body` is empty. Failing to
+/// show one is logged and otherwise ignored.
+pub fn notify(title: &str, body: impl Into<String>) {
+    let body = body.into();
+    let mut notification = Notification::new("com.riskycase.autocampnet").title(title);
+    if !body.is_empty() {
+        notification = notification.body(body);
+    }
+    notification.show().or_report("Notification");
+}
+
+pub fn main_window(app: &tauri::AppHandle) -> AppResult<tauri::Window> {
+    app.get_window("main").ok_or(AppError::NoWindow)
+}
+
+/// Brings up the main window, also raising and focusing it when `focus` is
+/// set, as when it is opened from the tray
+pub fn show_main_window(app: &tauri::AppHandle, focus: bool) {
+    main_window(app)
+        .and_then(|window| {
+            window.show()?;
+            if focus {
+                window.unminimize()?;
+                window.set_focus()?;
+            }
+            Ok(())
+        })
+        .or_report("Main window");
+}
+
+/// Sets the tray icon to a bundled resource
+pub fn set_tray_icon_file(app: &tauri::AppHandle, resource: &str) {
+    app.path_resolver()
+        .resolve_resource(resource)
+        .ok_or(AppError::NoPath("resource"))
+        .and_then(|icon| Ok(app.tray_handle().set_icon(tauri::Icon::File(icon))?))
+        .or_report("Tray icon");
+}
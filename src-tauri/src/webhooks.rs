@@ -0,0 +1,298 @@
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::hooks::HookEvent;
+use crate::secret::Secret;
+
+/// Deliveries that still fail after this long are given up on
+const MAX_AGE_HOURS: i64 = 24;
+
+/// Longest wait between retries of a failing delivery
+const MAX_BACKOFF_SECS: i64 = 3600;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, TS)]
+pub struct WebhookTarget {
+    /// Queued deliveries find their target by this, so that editing the
+    /// URL does not orphan them. Filled in when left empty.
+    #[serde(default)]
+    pub id: String,
+    pub url: String,
+    /// Key for the `X-Autocampnet-Signature` header, which is left out
+    /// when there is no secret
+    #[serde(default)]
+    pub secret: Option<Secret>,
+    /// Names of the events to send, as used for hooks (`login`,
+    /// `quota_threshold`, ...). Every event is sent when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// Gives targets without an id one of their own, returning whether any
+/// was missing so that the settings get saved with them
+pub fn assign_ids(targets: &mut [WebhookTarget]) -> bool {
+    let mut assigned = false;
+    for target in targets.iter_mut().filter(|target| target.id.is_empty()) {
+        target.id = format!("{:016x}", rand::random::<u64>());
+        assigned = true;
+    }
+    assigned
+}
+
+impl WebhookTarget {
+    fn wants(&self, event: HookEvent) -> bool {
+        self.events.is_empty() || self.events.iter().any(|name| name == event.name())
+    }
+}
+
+/// One POST waiting to go out. Only the target's id is kept so that
+/// secrets stay in the settings file, the target is looked up again when
+/// sending.
+#[derive(Serialize, Deserialize, Clone)]
+struct Delivery {
+    id: String,
+    target: String,
+    event: String,
+    body: String,
+    created: i64,
+    attempts: u32,
+    /// Unix milliseconds of the next attempt
+    next_attempt: i64,
+}
+
+/// Queue of webhook deliveries, written to disk on every change so that
+/// events raised while offline go out once the network is back.
+pub struct Webhooks {
+    queue_file: PathBuf,
+    queue: Mutex<Vec<Delivery>>,
+    wake: Condvar,
+}
+
+impl Webhooks {
+    pub fn new(queue_file: PathBuf) -> Self {
+        let queue = std::fs::read_to_string(&queue_file)
+            .ok()
+            .and_then(|queue| serde_json::from_str(&queue).ok())
+            .unwrap_or_default();
+        Webhooks {
+            queue_file,
+            queue: Mutex::new(queue),
+            wake: Condvar::new(),
+        }
+    }
+
+    /// Queues `event` for every target that wants it
+    pub fn enqueue(&self, targets: &[WebhookTarget], event: HookEvent, details: &[(&str, String)]) {
+        let now = chrono::Local::now();
+        let body = serde_json::json!({
+            "event": event.name(),
+            "time": now.to_rfc3339(),
+            "details": details
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<BTreeMap<String, String>>(),
+        })
+        .to_string();
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        for target in targets.iter().filter(|target| target.wants(event)) {
+            queue.push(Delivery {
+                id: format!("{:016x}", rand::random::<u64>()),
+                target: target.id.to_string(),
+                event: event.name().to_string(),
+                body: body.to_string(),
+                created: now.timestamp_millis(),
+                attempts: 0,
+                next_attempt: now.timestamp_millis(),
+            });
+        }
+        self.save(&queue);
+        self.wake.notify_all();
+    }
+
+    /// Sends every delivery that is due, in the order they were queued.
+    /// Failed ones are retried with a growing delay.
+    pub fn deliver(&self, client: &Client, targets: &[WebhookTarget]) {
+        let now = chrono::Local::now().timestamp_millis();
+        let due: Vec<Delivery> = self
+            .queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|delivery| delivery.next_attempt <= now)
+            .cloned()
+            .collect();
+        for delivery in due {
+            let target = targets.iter().find(|target| target.id == delivery.target);
+            let sent = match target {
+                Some(target) => send(client, target, &delivery),
+                // The target was removed from settings
+                None => Sent::Rejected,
+            };
+            let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+            let position = queue.iter().position(|queued| queued.id == delivery.id);
+            if let Some(position) = position {
+                let expired = chrono::Local::now().timestamp_millis() - queue[position].created
+                    > MAX_AGE_HOURS * 3600 * 1000;
+                if sent != Sent::Failed || expired {
+                    queue.remove(position);
+                } else {
+                    let queued = &mut queue[position];
+                    queued.attempts += 1;
+                    let backoff = (10i64 << queued.attempts.min(12)).min(MAX_BACKOFF_SECS);
+                    queued.next_attempt = chrono::Local::now().timestamp_millis() + backoff * 1000;
+                }
+                self.save(&queue);
+            }
+        }
+    }
+
+    /// Blocks until the next delivery is due or a new one is queued, and at
+    /// most a minute so that a network coming back is noticed.
+    pub fn wait(&self) {
+        let queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = chrono::Local::now().timestamp_millis();
+        let wait = queue
+            .iter()
+            .map(|delivery| (delivery.next_attempt - now).max(0))
+            .min()
+            .unwrap_or(60_000)
+            .min(60_000);
+        if wait > 0 {
+            drop(
+                self.wake
+                    .wait_timeout(queue, Duration::from_millis(wait as u64))
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+        }
+    }
+
+    /// Lets the queue be used again after a panic while it was locked
+    pub fn clear_poison(&self) {
+        self.queue.clear_poison();
+    }
+
+    fn save(&self, queue: &[Delivery]) {
+        let temp_file = self.queue_file.with_extension("json.tmp");
+        let saved = std::fs::File::create(&temp_file)
+            .and_then(|mut file| write!(&mut file, "{}", serde_json::to_string(queue).unwrap()));
+        if saved.is_ok() {
+            std::fs::rename(&temp_file, &self.queue_file).ok();
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Sent {
+    Delivered,
+    /// The target will not take it, trying again would not help
+    Rejected,
+    Failed,
+}
+
+fn send(client: &Client, target: &WebhookTarget, delivery: &Delivery) -> Sent {
+    let mut request = client
+        .post(&target.url)
+        .header("Content-Type", "application/json")
+        .header("X-Autocampnet-Event", &delivery.event)
+        .header("X-Autocampnet-Delivery", &delivery.id)
+        .body(delivery.body.to_string());
+    if let Some(secret) = &target.secret {
+        request = request.header(
+            "X-Autocampnet-Signature",
+            sign(secret.expose(), &delivery.body),
+        );
+    }
+    match request.send() {
+        Ok(response) if response.status().is_success() => Sent::Delivered,
+        Ok(response)
+            if response.status().is_client_error()
+                && response.status() != reqwest::StatusCode::REQUEST_TIMEOUT
+                && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+        {
+            Sent::Rejected
+        }
+        // A URL that does not parse will not start working later
+        Err(err) if err.is_builder() => Sent::Rejected,
+        _ => Sent::Failed,
+    }
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of the body
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body.as_bytes());
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{Canned, FixtureServer};
+
+    fn queue_file() -> PathBuf {
+        std::env::temp_dir().join(format!("webhooks-{:016x}.json", rand::random::<u64>()))
+    }
+
+    fn target(url: &str) -> WebhookTarget {
+        WebhookTarget {
+            id: "".to_string(),
+            url: url.to_string(),
+            secret: Some(Secret::new("hunter2".to_string())),
+            events: vec!["login".to_string()],
+        }
+    }
+
+    #[test]
+    fn delivery_follows_its_target_to_a_new_url() {
+        let server = FixtureServer::start(&[("POST /new", Canned::ok(""))]);
+        let mut targets = vec![target("http://127.0.0.1:9/old")];
+        assert!(assign_ids(&mut targets));
+        assert!(!assign_ids(&mut targets));
+        let queue_file = queue_file();
+        let webhooks = Webhooks::new(queue_file.clone());
+        webhooks.enqueue(
+            &targets,
+            HookEvent::Login,
+            &[("ip", "10.0.0.2".to_string())],
+        );
+        webhooks.enqueue(&targets, HookEvent::Logout, &[]);
+        targets[0].url = server.endpoint.to_string() + "/new";
+        // The queue survives a restart
+        let webhooks = Webhooks::new(queue_file.clone());
+        webhooks.deliver(&Client::new(), &targets);
+        assert!(webhooks.queue.lock().unwrap().is_empty());
+        let received = server.received();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].headers["x-autocampnet-event"], "login");
+        assert_eq!(
+            received[0].headers["x-autocampnet-signature"],
+            sign("hunter2", &received[0].body)
+        );
+        std::fs::remove_file(queue_file).ok();
+    }
+
+    #[test]
+    fn delivery_for_a_removed_target_is_dropped() {
+        let mut targets = vec![target("http://127.0.0.1:9/")];
+        assign_ids(&mut targets);
+        let queue_file = queue_file();
+        let webhooks = Webhooks::new(queue_file.clone());
+        webhooks.enqueue(&targets, HookEvent::Login, &[]);
+        webhooks.deliver(&Client::new(), &[]);
+        assert!(webhooks.queue.lock().unwrap().is_empty());
+        std::fs::remove_file(queue_file).ok();
+    }
+}
@@ -0,0 +1,62 @@
+import { invoke } from "@tauri-apps/api";
+import { listen as listenEvent, Event } from "@tauri-apps/api/event";
+import {
+    ActiveSession,
+    AccountDetails,
+    AppEvent,
+    AppStatus,
+    CredentialCheck,
+    LocalInterface,
+    Settings,
+} from "./types";
+
+type EventName = AppEvent["event"];
+
+type Payload<E extends EventName> = Extract<
+    AppEvent,
+    { event: E }
+> extends { payload: infer P }
+    ? P
+    : null;
+
+// Event names and payloads come from the generated AppEvent, so listening
+// for an event the backend does not send fails to type check
+export function listen<E extends EventName>(
+    event: E,
+    handler: (payload: Payload<E>) => void
+) {
+    return listenEvent(event, (received: Event<Payload<E>>) =>
+        handler(received.payload)
+    );
+}
+
+// Credentials and passwords are passed URL encoded, the same way they are
+// saved. A null password keeps the saved one.
+export const commands = {
+    getStatus: () => invoke<AppStatus>("get_status"),
+    credentialCheck: (
+        username: string,
+        password: string | null,
+        captiveLogin: boolean
+    ) =>
+        invoke<CredentialCheck>("credential_check", {
+            username,
+            password,
+            captiveLogin,
+        }),
+    saveCredentials: (username: string, password: string | null) =>
+        invoke<void>("save_credentials", { username, password }),
+    setAutolaunch: (enabled: boolean) =>
+        invoke<void>("set_autolaunch", { enabled }),
+    minimise: () => invoke<void>("minimise"),
+    getSettings: () => invoke<Settings>("get_settings"),
+    saveSettings: (settings: Settings) =>
+        invoke<void>("save_settings", { settings }),
+    getAccountDetails: () => invoke<AccountDetails>("get_account_details"),
+    listSessions: () => invoke<ActiveSession[]>("list_sessions"),
+    terminateSession: (id: string) =>
+        invoke<void>("terminate_session", { id }),
+    changePassword: (currentPassword: string, newPassword: string) =>
+        invoke<void>("change_password", { currentPassword, newPassword }),
+    listInterfaces: () => invoke<LocalInterface[]>("list_interfaces"),
+};
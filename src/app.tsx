@@ -1,11 +1,22 @@
 import styles from "./app.module.scss";
-import { emit, listen, Event } from "@tauri-apps/api/event";
+import { commands, listen } from "./api";
 import { Login } from "./components/login/login";
 import { ElevatedCard, ToastContainer } from "@cred/neopop-web/lib/components";
 import { useEffect, useState } from "preact/hooks";
 import { DataBalance } from "./components/dataBalance/dataBalance";
-import { Credentials, Traffic, TrafficUnits } from "./types";
+import {
+    AccountDetails,
+    ConnectionState,
+    Credentials,
+    Quota,
+    ScheduleTransition,
+    TlsFailure,
+    Traffic,
+    TrafficUnits,
+} from "./types";
 import { Credits } from "./components/credits/credits";
+import { Sessions } from "./components/sessions/sessions";
+import { ChangePassword } from "./components/changePassword/changePassword";
 
 import initLogo from "./assets/logos/bits-goa.png";
 import initBG from "./assets/backgrounds/bits-goa.jpg";
@@ -13,7 +24,7 @@ import initBG from "./assets/backgrounds/bits-goa.jpg";
 export function App() {
     const [credentials, setCredentials] = useState<Credentials>({
         username: "",
-        password: "",
+        has_password: false,
     });
 
     const [traffic, setTraffic] = useState<Traffic>({
@@ -34,29 +45,55 @@ export function App() {
     const [logo, setLogo] = useState(initLogo);
     const [BG, setBG] = useState(initBG);
     const [autolaunch, setAutolaunch] = useState(false);
+    const [nextRetry, setNextRetry] = useState<number | null>(null);
+    const [connectionState, setConnectionState] =
+        useState<ConnectionState>("disconnected");
+    const [scheduleTransition, setScheduleTransition] =
+        useState<ScheduleTransition | null>(null);
+    const [tlsFailure, setTlsFailure] = useState<TlsFailure | null>(null);
+    const [quotas, setQuotas] = useState<Quota[]>([]);
+    const [accountDetails, setAccountDetails] =
+        useState<AccountDetails | null>(null);
 
-    useEffect(() => {
-        listen("credentials", (creds: Event<Credentials>) => {
-            setCredentials({
-                username: decodeURIComponent(creds.payload.username),
-                password: decodeURIComponent(creds.payload.password),
-            });
-        });
-        listen("traffic", (traffic: Event<Traffic>) => {
-            setTraffic(traffic.payload);
-        });
-        listen("traffic_units", (traffic_units: Event<TrafficUnits>) => {
-            setTrafficUnits(traffic_units.payload);
+    const updateCredentials = (creds: Credentials) =>
+        setCredentials({
+            username: decodeURIComponent(creds.username),
+            has_password: creds.has_password,
         });
-        listen("autolaunch", (enabled: Event<boolean>) => {
-            setAutolaunch(enabled.payload);
+
+    useEffect(() => {
+        listen("credentials", updateCredentials);
+        listen("traffic", setTraffic);
+        listen("traffic_units", setTrafficUnits);
+        listen("autolaunch", setAutolaunch);
+        listen("next_retry", setNextRetry);
+        listen("connection_state", setConnectionState);
+        listen("schedule_transition", setScheduleTransition);
+        listen("tls_failure", setTlsFailure);
+        listen("quotas", setQuotas);
+        listen("account_details", setAccountDetails);
+        commands.getStatus().then((status) => {
+            updateCredentials(status.credentials);
+            setTraffic(status.traffic);
+            setTrafficUnits(status.traffic_units);
+            setQuotas(status.quotas);
+            setAccountDetails(status.account_details);
+            setAutolaunch(status.autolaunch);
+            setNextRetry(status.next_retry);
+            setConnectionState(status.connection_state);
+            setScheduleTransition(status.schedule_transition);
+            setTlsFailure(status.tls_failure);
         });
+        commands
+            .getAccountDetails()
+            .then(setAccountDetails)
+            .catch(() => setAccountDetails(null));
         document.documentElement.style.setProperty(
             "background-image",
             `url(${BG})`
         );
         document.addEventListener("visibilitychange", () => {
-            if (document.visibilityState === "hidden") emit("minimise");
+            if (document.visibilityState === "hidden") commands.minimise();
         });
     }, []);
 
@@ -76,13 +113,24 @@ export function App() {
                         setCredentials={setCredentials}
                         logo={logo}
                         autolaunch={autolaunch}
+                        nextRetry={nextRetry}
+                        scheduleTransition={scheduleTransition}
+                        tlsFailure={
+                            connectionState === "certificate_error"
+                                ? tlsFailure
+                                : null
+                        }
                     />
                     <DataBalance
                         credentials={credentials}
                         traffic={traffic}
                         trafficUnits={trafficUnits}
+                        quotas={quotas}
+                        accountDetails={accountDetails}
                     />
                 </div>
+                <Sessions credentials={credentials} />
+                <ChangePassword credentials={credentials} />
                 <Credits />
             </ElevatedCard>
         </div>
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Plan details shown on the user portal next to the data balance. Every
+ * field is optional since deployments differ in what they list.
+ */
+export type AccountDetails = { group: string | null, policy: string | null, expiry: string | null, bandwidth: string | null, upload: string | null, download: string | null, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * One login of this account as listed on the user portal
+ */
+export type ActiveSession = { 
+/**
+ * What the portal expects back to log this session out
+ */
+id: string, ip: string | null, mac: string | null, start_time: string | null, data_used: string | null, };
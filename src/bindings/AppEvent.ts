@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AccountDetails } from "./AccountDetails";
+import type { ConnectionState } from "./ConnectionState";
+import type { Credentials } from "./Credentials";
+import type { Quota } from "./Quota";
+import type { ScheduleTransition } from "./ScheduleTransition";
+import type { TlsFailure } from "./TlsFailure";
+import type { Traffic } from "./Traffic";
+import type { TrafficUnits } from "./TrafficUnits";
+
+/**
+ * Events sent to the window, the tag being the event name. The frontend
+ * listens through the generated type, so a payload cannot change on one
+ * side only.
+ */
+export type AppEvent = { "event": "credentials", "payload": Credentials } | { "event": "traffic", "payload": Traffic } | { "event": "traffic_units", "payload": TrafficUnits } | { "event": "quotas", "payload": Array<Quota> } | { "event": "account_details", "payload": AccountDetails | null } | { "event": "autolaunch", "payload": boolean } | { "event": "next_retry", "payload": number | null } | { "event": "connection_state", "payload": ConnectionState } | { "event": "schedule_transition", "payload": ScheduleTransition | null } | { "event": "tls_failure", "payload": TlsFailure } | { "event": "session_limit" };
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AccountDetails } from "./AccountDetails";
+import type { ConnectionState } from "./ConnectionState";
+import type { Credentials } from "./Credentials";
+import type { Quota } from "./Quota";
+import type { ScheduleTransition } from "./ScheduleTransition";
+import type { TlsFailure } from "./TlsFailure";
+import type { Traffic } from "./Traffic";
+import type { TrafficUnits } from "./TrafficUnits";
+
+/**
+ * Everything the window shows, for filling it in when it opens
+ */
+export type AppStatus = { credentials: Credentials, traffic: Traffic, traffic_units: TrafficUnits, quotas: Array<Quota>, account_details: AccountDetails | null, autolaunch: boolean, 
+/**
+ * Unix milliseconds of the next login attempt
+ */
+next_retry: number | null, connection_state: ConnectionState, schedule_transition: ScheduleTransition | null, 
+/**
+ * Why the login endpoint or user portal was refused, while that is
+ * what keeps the app from connecting
+ */
+tls_failure: TlsFailure | null, };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Vendor } from "./Vendor";
+
+/**
+ * The network the app logs in to and which captive portal guards it
+ */
+export type CampusProfile = { name: string, vendor: Vendor, login_endpoint: string, 
+/**
+ * Sophos user portal, where usage and account details are read from
+ */
+portal_endpoint: string, 
+/**
+ * Captive portal zone, only used by pfSense
+ */
+zone: string | null, 
+/**
+ * Recipe file, relative to the config directory unless absolute. Only
+ * used by the recipe vendor.
+ */
+recipe: string | null, };
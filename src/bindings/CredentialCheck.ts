@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Quota } from "./Quota";
+
+/**
+ * What checking credentials found out about the account, so the window can
+ * warn when the password is right but there is no quota left to log in with
+ */
+export type CredentialCheck = { 
+/**
+ * The quota closest to running out, when the user portal reported one
+ */
+quota: Quota | null, quota_exhausted: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Why credentials were not checked or saved
+ */
+export type CredentialError = "NOPASSWORD" | "PAUSED" | "SCHEDULE" | "UNTRUSTED" | "INVALIDCRED" | "CERTIFICATE" | "NOSOPHOS" | "NOTSAVED" | "UNVERIFIABLE" | "UNKNOWN";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Saved credentials as the window sees them, without the password
+ */
+export type Credentials = { username: string, has_password: boolean, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Commands to run when things happen, passed to `sh -c` (`cmd /C` on
+ * Windows). Events without a command are skipped.
+ */
+export type HookSettings = { on_login: string | null, on_logout: string | null, on_login_failed: string | null, on_quota_threshold: string | null, on_quota_reset: string | null, on_network_lost: string | null, 
+/**
+ * Seconds a command may run before it is killed
+ */
+timeout_secs: number, };
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProxySetting } from "./ProxySetting";
+
+export type HttpSettings = { connect_timeout_secs: number, 
+/**
+ * Upper bound for a whole request, including reading the body
+ */
+timeout_secs: number, 
+/**
+ * How long an idle keep-alive connection is kept around for reuse
+ */
+pool_idle_timeout_secs: number, cookie_store: boolean, 
+/**
+ * Proxy for the connectivity probe and the user portal. The login
+ * endpoint is a captive portal and is always reached directly when a
+ * custom proxy is set.
+ */
+proxy: ProxySetting, 
+/**
+ * Local address or interface name the login client connects from, for
+ * machines that are on campus over only one of several interfaces
+ */
+bind: string | null, };
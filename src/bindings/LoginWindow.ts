@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Day } from "./Day";
+
+/**
+ * Hours during which the app may hold a session, as `HH:MM` times. A
+ * window ending before it starts runs past midnight into the next day.
+ */
+export type LoginWindow = { 
+/**
+ * Days the window starts on, every day when empty
+ */
+days: Array<Day>, start: string, end: string, };
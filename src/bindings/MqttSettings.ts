@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Secret } from "./Secret";
+
+export type MqttSettings = { enabled: boolean, host: string, port: number, username: string | null, password: Secret | null, client_id: string, 
+/**
+ * Prefix of every topic published or subscribed to
+ */
+base_topic: string, 
+/**
+ * Announce sensors and buttons to Home Assistant
+ */
+discovery: boolean, discovery_prefix: string, };
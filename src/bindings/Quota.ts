@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { QuotaKind } from "./QuotaKind";
+import type { QuotaPeriod } from "./QuotaPeriod";
+
+/**
+ * One limit the portal enforces on the account
+ */
+export type Quota = { kind: QuotaKind, period: QuotaPeriod, limit: number, used: number, remaining: number, unit: string, resets_at: string | null, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How the login loop reschedules itself after something goes wrong.
+ */
+export type RetryPolicy = { 
+/**
+ * Retries after a failed login before giving up until the next reconnect
+ */
+max_retries: number, initial_delay_secs: number, max_delay_secs: number, multiplier: number, 
+/**
+ * Fraction of the delay that is randomly added or removed
+ */
+jitter: number, 
+/**
+ * Retries after the portal rejects the password. Kept separate since
+ * repeatedly posting a wrong password can get the account locked.
+ */
+invalid_credentials_retries: number, invalid_credentials_delay_secs: number, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LoginWindow } from "./LoginWindow";
+
+/**
+ * When the app is allowed to be logged in. Outside the windows it logs out
+ * and stays idle until the next one opens.
+ */
+export type Schedule = { enabled: boolean, windows: Array<LoginWindow>, };
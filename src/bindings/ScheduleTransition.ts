@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The next change of the login schedule, `at` being unix milliseconds
+ */
+export type ScheduleTransition = { at: number, 
+/**
+ * Whether logging in is allowed from then on
+ */
+allowed: boolean, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A password or key. The memory is wiped when dropped, and `Debug` prints
+ * a placeholder so it cannot end up in a log by accident. Serializing
+ * writes the value, which is only meant for files on disk and never for
+ * events sent to the window.
+ */
+export type Secret = string;
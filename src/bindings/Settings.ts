@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CampusProfile } from "./CampusProfile";
+import type { HookSettings } from "./HookSettings";
+import type { HttpSettings } from "./HttpSettings";
+import type { MqttSettings } from "./MqttSettings";
+import type { RetryPolicy } from "./RetryPolicy";
+import type { Schedule } from "./Schedule";
+import type { StatusFileSettings } from "./StatusFileSettings";
+import type { TlsSettings } from "./TlsSettings";
+import type { TrayIconSettings } from "./TrayIconSettings";
+import type { TrustPolicy } from "./TrustPolicy";
+import type { WebhookTarget } from "./WebhookTarget";
+
+export type Settings = { campus: CampusProfile, trust: TrustPolicy, retry: RetryPolicy, http: HttpSettings, tls: TlsSettings, tray_icon: TrayIconSettings, schedule: Schedule, hooks: HookSettings, webhooks: Array<WebhookTarget>, mqtt: MqttSettings, status_file: StatusFileSettings, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type StatusFileSettings = { enabled: boolean, 
+/**
+ * One line summary written to `status.txt`, with `{state}`, `{status}`,
+ * `{used}`, `{remaining}`, `{limit}`, `{unit}`, `{remaining_text}` and
+ * `{percentage}` filled in. Only the JSON file is written without it.
+ */
+template: string | null, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Why the last handshake was refused, kept so that a failed request can be
+ * reported as a certificate problem rather than the portal being down.
+ */
+export type TlsFailure = { endpoint: string, reason: string, 
+/**
+ * Pin of the certificate that was actually served, to help set up pinning
+ */
+served_pin: string | null, };
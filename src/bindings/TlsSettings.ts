@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TlsSettings = { 
+/**
+ * PEM file with certificate authorities to trust on top of the system ones
+ */
+ca_bundle: string | null, 
+/**
+ * Base64 SHA-256 of the login endpoint's public key, as in
+ * `sha256//<base64>` (the prefix is optional)
+ */
+login_pin: string | null, portal_pin: string | null, };
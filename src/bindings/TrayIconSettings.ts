@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TrayIconSettings = { 
+/**
+ * Draw the icon at runtime instead of using the bundled ones
+ */
+dynamic: boolean, 
+/**
+ * Print the remaining percentage over the icon
+ */
+show_percentage: boolean, };
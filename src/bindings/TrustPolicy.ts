@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Rules a network has to satisfy before credentials are sent to it. Every
+ * rule is optional, an empty policy trusts every network.
+ */
+export type TrustPolicy = { 
+/**
+ * Wi-Fi network names on which logging in is allowed
+ */
+allowed_ssids: Array<string>, 
+/**
+ * Default gateway addresses (`10.1.0.1`) or subnets (`10.1.0.0/16`)
+ */
+allowed_gateways: Array<string>, 
+/**
+ * Hex encoded SHA-256 of the certificate served by the login endpoint.
+ * Checked on every handshake with it, so credentials only ever go out
+ * over a connection to that certificate.
+ */
+certificate_fingerprint: string | null, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Secret } from "./Secret";
+
+export type WebhookTarget = { 
+/**
+ * Queued deliveries find their target by this, so that editing the
+ * URL does not orphan them. Filled in when left empty.
+ */
+id: string, url: string, 
+/**
+ * Key for the `X-Autocampnet-Signature` header, which is left out
+ * when there is no secret
+ */
+secret: Secret | null, 
+/**
+ * Names of the events to send, as used for hooks (`login`,
+ * `quota_threshold`, ...). Every event is sent when empty.
+ */
+events: Array<string>, };
@@ -0,0 +1,111 @@
+import {
+    Button,
+    InputField,
+    showToast,
+} from "@cred/neopop-web/lib/components";
+import { useState } from "preact/hooks";
+import { ChangeEvent } from "preact/compat";
+import { commands } from "../../api";
+import { Credentials } from "../../types";
+
+import styles from "./changePassword.module.scss";
+
+const errors: { [code: string]: string } = {
+    INVALIDCRED: "Current password does not match the saved one!",
+    NOSESSION: "Could not reach the user portal!",
+    REJECTED: "The portal did not accept the new password!",
+    NOTSAVED:
+        "Password was changed, but could not be saved to disk!",
+    UNVERIFIED:
+        "Password was changed and saved, but logging in with it failed!",
+};
+
+export function ChangePassword(props: { credentials: Credentials }) {
+    const [open, setOpen] = useState(false);
+    const [currentPassword, setCurrentPassword] = useState("");
+    const [newPassword, setNewPassword] = useState("");
+
+    if (
+        props.credentials.username === "" ||
+        !props.credentials.has_password
+    ) {
+        return <></>;
+    }
+
+    return open ? (
+        <div class={styles.changePasswordContainer}>
+            <InputField
+                label="Current password"
+                id="current_password"
+                // @ts-ignore
+                type="password"
+                onChange={(event: ChangeEvent<HTMLInputElement>) =>
+                    setCurrentPassword((event.target as HTMLInputElement).value)
+                }
+                value={currentPassword}
+            />
+            <InputField
+                label="New password"
+                id="new_password"
+                // @ts-ignore
+                type="password"
+                onChange={(event: ChangeEvent<HTMLInputElement>) =>
+                    setNewPassword((event.target as HTMLInputElement).value)
+                }
+                value={newPassword}
+            />
+            <Button
+                variant="primary"
+                kind="elevated"
+                style={{
+                    alignSelf: "flex-end",
+                }}
+                colorMode={"light"}
+                onClick={() => {
+                    showToast("Changing password", {
+                        type: "warning",
+                        autoCloseTime: 3000,
+                        content: "Changing password",
+                    });
+                    commands
+                        .changePassword(
+                            encodeURIComponent(currentPassword),
+                            encodeURIComponent(newPassword)
+                        )
+                        .then(() => {
+                            showToast("Password changed!", {
+                                type: "success",
+                                autoCloseTime: 3000,
+                                content: "Password changed!",
+                            });
+                            setCurrentPassword("");
+                            setNewPassword("");
+                            setOpen(false);
+                        })
+                        .catch((err) => {
+                            showToast("Could not change password!", {
+                                type: "error",
+                                autoCloseTime: 5000,
+                                content:
+                                    errors[err] ?? "Could not change password!",
+                            });
+                        });
+                }}
+            >
+                Change
+            </Button>
+        </div>
+    ) : (
+        <div class={styles.changePasswordContainer}>
+            <Button
+                variant="secondary"
+                kind="flat"
+                size="small"
+                colorMode={"dark"}
+                onClick={() => setOpen(true)}
+            >
+                Change password
+            </Button>
+        </div>
+    );
+}
@@ -1,6 +1,12 @@
 import { ScoreMeter } from "@cred/neopop-web/lib/components";
 import { useState } from "preact/hooks";
-import { Credentials, Traffic, TrafficUnits } from "../../types";
+import {
+    AccountDetails,
+    Credentials,
+    Quota,
+    Traffic,
+    TrafficUnits,
+} from "../../types";
 
 import styles from "./dataBalance.module.scss";
 
@@ -16,16 +22,36 @@ function DataInfo(props: { title: string; amount: number; unit: string }) {
     );
 }
 
+function AccountInfo(props: { title: string; value: string | null }) {
+    return props.value ? (
+        <div class={styles.dataInfo}>
+            <div>{props.title}</div>
+            <div>{props.value}</div>
+        </div>
+    ) : (
+        <></>
+    );
+}
+
+const quotaNames = {
+    data: "Data",
+    upload: "Upload",
+    download: "Download",
+    time: "Surfing time",
+};
+
 export function DataBalance(props: {
     credentials: Credentials;
     traffic: Traffic;
     trafficUnits: TrafficUnits;
+    quotas: Quota[];
+    accountDetails: AccountDetails | null;
 }) {
     const [toShow, show] = useState<boolean>(false);
 
     if (
         props.credentials.username === "" ||
-        props.credentials.password === "" ||
+        !props.credentials.has_password ||
         props.traffic.total === 0
     ) {
         show(false);
@@ -67,6 +93,38 @@ export function DataBalance(props: {
                     unit={props.trafficUnits.remaining}
                 />
             </div>
+            {props.quotas.length > 1 ? (
+                <div class={styles.infoContainer}>
+                    {props.quotas.map((quota) => (
+                        <DataInfo
+                            title={`${quotaNames[quota.kind]} (${
+                                quota.period
+                            }) left:`}
+                            amount={quota.remaining}
+                            unit={quota.unit}
+                        />
+                    ))}
+                </div>
+            ) : (
+                <></>
+            )}
+            {props.accountDetails ? (
+                <div class={styles.infoContainer}>
+                    <AccountInfo
+                        title="Plan:"
+                        value={
+                            props.accountDetails.policy ??
+                            props.accountDetails.group
+                        }
+                    />
+                    <AccountInfo
+                        title="Expires:"
+                        value={props.accountDetails.expiry}
+                    />
+                </div>
+            ) : (
+                <></>
+            )}
         </div>
     ) : (
         <></>
@@ -6,36 +6,44 @@ import {
     Toggle,
 } from "@cred/neopop-web/lib/components";
 import { StateUpdater, useEffect, useState } from "preact/hooks";
-import { emit } from "@tauri-apps/api/event";
 
 import styles from "./login.module.scss";
 
 import { ChangeEvent } from "preact/compat";
-import { invoke } from "@tauri-apps/api";
+import { commands } from "../../api";
+import {
+    CredentialError,
+    Credentials,
+    ScheduleTransition,
+    TlsFailure,
+} from "../../types";
 
 export function Login(props: {
-    credentials: {
-        username: string;
-        password: string;
-    };
-    setCredentials: StateUpdater<{
-        username: string;
-        password: string;
-    }>;
+    credentials: Credentials;
+    setCredentials: StateUpdater<Credentials>;
     logo: string;
     autolaunch: boolean;
+    nextRetry: number | null;
+    scheduleTransition: ScheduleTransition | null;
+    tlsFailure: TlsFailure | null;
 }) {
     const [localUsername, setLocalUsername] = useState(
         props.credentials.username
     );
-    const [localPassword, setLocalPassword] = useState(
-        props.credentials.password
-    );
+    // Only ever holds a newly typed password, the saved one stays in the
+    // backend
+    const [localPassword, setLocalPassword] = useState("");
     const [capsLock, setCapsLock] = useState(false);
+    // Set when the user portal could not check the credentials, the next
+    // save then tries them with a campnet login
+    const [loginToVerify, setLoginToVerify] = useState(false);
     useEffect(() => {
         setLocalUsername(props.credentials.username);
-        setLocalPassword(props.credentials.password);
+        setLocalPassword("");
     }, [props.credentials]);
+    useEffect(() => {
+        setLoginToVerify(false);
+    }, [localUsername, localPassword]);
     useEffect(() => {
         window.addEventListener("keyup", (event) =>
             setCapsLock(event.getModifierState("CapsLock"))
@@ -69,7 +77,11 @@ export function Login(props: {
                 <InputField
                     label="Password"
                     id="password"
-                    placeholder="fdxxxxxxxx"
+                    placeholder={
+                        props.credentials.has_password
+                            ? "Saved, type to change"
+                            : "fdxxxxxxxx"
+                    }
                     // @ts-ignore
                     type="password"
                     onChange={(event: ChangeEvent<HTMLInputElement>) =>
@@ -92,6 +104,44 @@ export function Login(props: {
                         CapsLock is On!
                     </Tag>
                 )}
+                {props.nextRetry !== null && (
+                    <Tag
+                        colorConfig={{
+                            background: "#010B14",
+                            color: "#F08D32",
+                        }}
+                    >
+                        {`Next login attempt at ${new Date(
+                            props.nextRetry
+                        ).toLocaleTimeString()}`}
+                    </Tag>
+                )}
+                {props.scheduleTransition !== null && (
+                    <Tag
+                        colorConfig={{
+                            background: "#010B14",
+                            color: "#3498DB",
+                        }}
+                    >
+                        {`Login hours ${
+                            props.scheduleTransition.allowed ? "start" : "end"
+                        } ${new Date(
+                            props.scheduleTransition.at
+                        ).toLocaleString()}`}
+                    </Tag>
+                )}
+                {props.tlsFailure !== null && (
+                    <Tag
+                        colorConfig={{
+                            background: "#010B14",
+                            color: "#EE4D37",
+                        }}
+                    >
+                        {`Certificate problem with ${props.tlsFailure.endpoint}: ${props.tlsFailure.reason}`}
+                        {props.tlsFailure.served_pin !== null &&
+                            ` (served pin sha256//${props.tlsFailure.served_pin})`}
+                    </Tag>
+                )}
                 <div class={styles.autolaunchContainer}>
                     <span class={styles.autolaunchSwitch}>
                         <Toggle
@@ -100,8 +150,7 @@ export function Login(props: {
                             onChange={(
                                 event: ChangeEvent<HTMLInputElement>
                             ) => {
-                                emit(
-                                    "autolaunch",
+                                commands.setAutolaunch(
                                     (event.target as HTMLInputElement).checked
                                 );
                             }}
@@ -122,26 +171,54 @@ export function Login(props: {
                             autoCloseTime: 3000,
                             content: "Verifying credentials",
                         });
-                        invoke("credential_check", {
-                            username: encodeURIComponent(localUsername),
-                            password: encodeURIComponent(localPassword),
-                        })
-                            .then(() => {
-                                showToast("Credentias verified!", {
-                                    type: "success",
-                                    autoCloseTime: 3000,
-                                    content: "Credentias verified!",
-                                });
-                                props.setCredentials({
-                                    username: localUsername,
-                                    password: localPassword,
-                                });
-                                emit("save", {
-                                    username: encodeURIComponent(localUsername),
-                                    password: encodeURIComponent(localPassword),
-                                });
+                        const password =
+                            localPassword === ""
+                                ? null
+                                : encodeURIComponent(localPassword);
+                        const username = encodeURIComponent(localUsername);
+                        commands
+                            .credentialCheck(username, password, loginToVerify)
+                            .then((check) => {
+                                if (check.quota_exhausted) {
+                                    showToast("Quota exhausted!", {
+                                        type: "warning",
+                                        autoCloseTime: 5000,
+                                        content:
+                                            "Credentials are valid but no quota is left" +
+                                            (check.quota?.resets_at
+                                                ? ", resets " +
+                                                  check.quota.resets_at
+                                                : ""),
+                                    });
+                                } else {
+                                    showToast("Credentias verified!", {
+                                        type: "success",
+                                        autoCloseTime: 3000,
+                                        content: "Credentias verified!",
+                                    });
+                                }
+                                commands
+                                    .saveCredentials(username, password)
+                                    .then(() => {
+                                        props.setCredentials({
+                                            username: localUsername,
+                                            has_password: true,
+                                        });
+                                        setLocalPassword("");
+                                    })
+                                    .catch(() => {
+                                        showToast(
+                                            "Could not save credentials!",
+                                            {
+                                                type: "error",
+                                                autoCloseTime: 5000,
+                                                content:
+                                                    "Could not save credentials!",
+                                            }
+                                        );
+                                    });
                             })
-                            .catch((err) => {
+                            .catch((err: CredentialError) => {
                                 switch (err) {
                                     case "INVALIDCRED":
                                         showToast("Incorrect credentials", {
@@ -157,6 +234,56 @@ export function Login(props: {
                                             content: "Not on Sophos!",
                                         });
                                         break;
+                                    case "UNTRUSTED":
+                                        showToast("Network not trusted!", {
+                                            type: "error",
+                                            autoCloseTime: 3000,
+                                            content: "Network not trusted!",
+                                        });
+                                        break;
+                                    case "NOPASSWORD":
+                                        showToast("Password missing!", {
+                                            type: "error",
+                                            autoCloseTime: 3000,
+                                            content: "Enter the password!",
+                                        });
+                                        break;
+                                    case "PAUSED":
+                                        showToast("Auto-login paused!", {
+                                            type: "error",
+                                            autoCloseTime: 3000,
+                                            content:
+                                                "Resume auto-login from the tray first!",
+                                        });
+                                        break;
+                                    case "SCHEDULE":
+                                        showToast("Outside login hours!", {
+                                            type: "error",
+                                            autoCloseTime: 3000,
+                                            content:
+                                                "Logging in is not allowed right now!",
+                                        });
+                                        break;
+                                    case "CERTIFICATE":
+                                        showToast("Certificate problem!", {
+                                            type: "error",
+                                            autoCloseTime: 3000,
+                                            content:
+                                                "Login page certificate was rejected!",
+                                        });
+                                        break;
+                                    case "UNVERIFIABLE":
+                                        showToast(
+                                            "Could not verify credentials!",
+                                            {
+                                                type: "warning",
+                                                autoCloseTime: 5000,
+                                                content:
+                                                    "The user portal could not check them, log in to verify them",
+                                            }
+                                        );
+                                        setLoginToVerify(true);
+                                        break;
                                     case "UNKNOWN":
                                         showToast(
                                             "Could not verify credentials!",
@@ -172,7 +299,7 @@ export function Login(props: {
                             });
                     }}
                 >
-                    Save
+                    {loginToVerify ? "Log in to verify" : "Save"}
                 </Button>
             </div>
         </div>
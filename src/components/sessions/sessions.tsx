@@ -0,0 +1,101 @@
+import { Button, showToast } from "@cred/neopop-web/lib/components";
+import { useEffect, useState } from "preact/hooks";
+import { commands, listen } from "../../api";
+import { ActiveSession, Credentials } from "../../types";
+
+import styles from "./sessions.module.scss";
+
+export function Sessions(props: { credentials: Credentials }) {
+    const [sessions, setSessions] = useState<ActiveSession[]>([]);
+
+    const refresh = () => {
+        commands
+            .listSessions()
+            .then(setSessions)
+            .catch(() => setSessions([]));
+    };
+
+    useEffect(() => {
+        listen("session_limit", () => {
+            showToast("Maximum login limit reached", {
+                type: "error",
+                autoCloseTime: 5000,
+                content: "Log out one of the other sessions to connect",
+            });
+            refresh();
+        });
+    }, []);
+
+    useEffect(() => {
+        if (
+            props.credentials.username !== "" &&
+            props.credentials.has_password
+        ) {
+            refresh();
+        } else {
+            setSessions([]);
+        }
+    }, [props.credentials]);
+
+    return sessions.length > 0 ? (
+        <div class={styles.sessionsContainer}>
+            <div class={styles.sessionsHeader}>
+                <span>Active sessions</span>
+                <Button
+                    variant="secondary"
+                    kind="flat"
+                    size="small"
+                    colorMode={"dark"}
+                    onClick={refresh}
+                >
+                    Refresh
+                </Button>
+            </div>
+            {sessions.map((session) => (
+                <div class={styles.session} key={session.id}>
+                    <div>
+                        <div>{session.ip ?? session.id}</div>
+                        <div class={styles.sessionDetail}>
+                            {[
+                                session.mac,
+                                session.start_time,
+                                session.data_used,
+                            ]
+                                .filter((detail) => detail !== null)
+                                .join(" · ")}
+                        </div>
+                    </div>
+                    <Button
+                        variant="primary"
+                        kind="elevated"
+                        size="small"
+                        colorMode={"light"}
+                        onClick={() => {
+                            commands
+                                .terminateSession(session.id)
+                                .then(() => {
+                                    showToast("Session logged out", {
+                                        type: "success",
+                                        autoCloseTime: 3000,
+                                        content: "Session logged out",
+                                    });
+                                    refresh();
+                                })
+                                .catch((err) => {
+                                    showToast("Could not log out session", {
+                                        type: "error",
+                                        autoCloseTime: 3000,
+                                        content: err,
+                                    });
+                                });
+                        }}
+                    >
+                        Log out
+                    </Button>
+                </div>
+            ))}
+        </div>
+    ) : (
+        <></>
+    );
+}
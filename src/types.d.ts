@@ -1,22 +1,35 @@
-type Credentials = {
-    username: string;
-    password: string;
-};
-
-type Traffic = {
-    total: number;
-    last: number;
-    current: number;
-    used: number;
-    remaining: number;
-};
-
-type TrafficUnits = {
-    total: string;
-    last: string;
-    current: string;
-    used: string;
-    remaining: string;
-};
-
-export { Credentials, Traffic, TrafficUnits };
+// Types shared with the backend are generated from the Rust structs, run
+// `npm run bindings` after changing those
+export type { AccountDetails } from "./bindings/AccountDetails";
+export type { ActiveSession } from "./bindings/ActiveSession";
+export type { AppEvent } from "./bindings/AppEvent";
+export type { AppStatus } from "./bindings/AppStatus";
+export type { CampusProfile } from "./bindings/CampusProfile";
+export type { ConnectionState } from "./bindings/ConnectionState";
+export type { CredentialCheck } from "./bindings/CredentialCheck";
+export type { CredentialError } from "./bindings/CredentialError";
+export type { Credentials } from "./bindings/Credentials";
+export type { Day } from "./bindings/Day";
+export type { HookSettings } from "./bindings/HookSettings";
+export type { HttpSettings } from "./bindings/HttpSettings";
+export type { LocalInterface } from "./bindings/LocalInterface";
+export type { LoginWindow } from "./bindings/LoginWindow";
+export type { MqttSettings } from "./bindings/MqttSettings";
+export type { ProxySetting } from "./bindings/ProxySetting";
+export type { Quota } from "./bindings/Quota";
+export type { QuotaKind } from "./bindings/QuotaKind";
+export type { QuotaPeriod } from "./bindings/QuotaPeriod";
+export type { RetryPolicy } from "./bindings/RetryPolicy";
+export type { Schedule } from "./bindings/Schedule";
+export type { ScheduleTransition } from "./bindings/ScheduleTransition";
+export type { Secret } from "./bindings/Secret";
+export type { Settings } from "./bindings/Settings";
+export type { StatusFileSettings } from "./bindings/StatusFileSettings";
+export type { TlsFailure } from "./bindings/TlsFailure";
+export type { TlsSettings } from "./bindings/TlsSettings";
+export type { Traffic } from "./bindings/Traffic";
+export type { TrafficUnits } from "./bindings/TrafficUnits";
+export type { TrayIconSettings } from "./bindings/TrayIconSettings";
+export type { TrustPolicy } from "./bindings/TrustPolicy";
+export type { Vendor } from "./bindings/Vendor";
+export type { WebhookTarget } from "./bindings/WebhookTarget";